@@ -10,6 +10,25 @@ pub static TOKEN_ACCOUNT: &[u8] = b"token_account";
 
 pub static TOKEN_PURCHASE: &[u8] = b"token_purchase";
 
+pub static SOL_VAULT: &[u8] = b"sol_vault";
+
+pub static VAULT_AUTHORITY: &[u8] = b"vault_authority";
+
+pub static GLOBAL_CONFIG: &[u8] = b"global_config";
+
+pub static ALLOWED_MINT: &[u8] = b"allowed_mint";
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// 创建 Raydium 池子时各账户租金的预留上限，由 finalize_sale 的调用者垫付，未用完的部分退还
+pub const POOL_CREATION_RENT: u64 = 100_000_000;
+
+// 暂停的最长时长，超过后已过结束时间的销售不再等待恢复，直接判定结果
+pub const MAX_PAUSE_DURATION: i64 = 7 * 24 * 60 * 60;
+
+// 协议手续费比例上限，保证成功的销售总有资金注入流动性
+pub const MAX_PROTOCOL_FEE_BPS: u64 = 1_000;
+
 
 
 // pub static Stake_CA: &str = "metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s"; //质押合约
@@ -0,0 +1,96 @@
+use anchor_lang::prelude::*;
+
+use crate::structures::PriceMode;
+
+/// 创建销售
+#[event]
+pub struct SaleCreated {
+    pub sale: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint: Pubkey,
+    pub buy_token_mint: Pubkey,
+    pub sale_amount: u64,
+    pub soft_cap: u64,
+    pub price_mode: PriceMode,
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    pub floor_price_numerator: u64,
+    pub native_payment: bool,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub timestamp: i64,
+}
+
+/// 买家购买，价格为本次实际成交价格
+#[event]
+pub struct TokensPurchased {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub token_mint: Pubkey,
+    pub buy_token_mint: Pubkey,
+    pub token_amount: u64,
+    pub payment_amount: u64,
+    pub price_numerator: u64,
+    pub price_denominator: u64,
+    pub remaining_amount: u64,
+    pub timestamp: i64,
+}
+
+/// 注入 Raydium 流动性
+#[event]
+pub struct PoolSeeded {
+    pub sale: Pubkey,
+    pub pool_state: Pubkey,
+    pub token_mint: Pubkey,
+    pub buy_token_mint: Pubkey,
+    pub token_amount: u64,
+    pub payment_amount: u64,
+    pub protocol_fee: u64,
+    pub open_time: u64,
+    pub timestamp: i64,
+}
+
+/// 买家领取已解锁的代币
+#[event]
+pub struct TokensClaimed {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub token_mint: Pubkey,
+    pub amount: u64,
+    pub claimed_amount: u64,
+    pub total_amount: u64,
+    pub timestamp: i64,
+}
+
+/// 销售失败或取消后买家退款
+#[event]
+pub struct Refunded {
+    pub sale: Pubkey,
+    pub buyer: Pubkey,
+    pub buy_token_mint: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+/// 所有者取回未售出代币或提取募集资金
+#[event]
+pub struct ProceedsWithdrawn {
+    pub sale: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint: Pubkey,
+    pub buy_token_mint: Pubkey,
+    pub unsold_amount: u64,
+    pub proceeds_amount: u64,
+    pub timestamp: i64,
+}
+
+/// 所有者取消销售
+#[event]
+pub struct SaleCancelled {
+    pub sale: Pubkey,
+    pub owner: Pubkey,
+    pub token_mint: Pubkey,
+    pub sold_amount: u64,
+    pub total_raised: u64,
+    pub timestamp: i64,
+}
@@ -1,5 +1,7 @@
 pub mod structures;
 pub mod constants;
+pub mod merkle;
+pub mod events;
 
 use constants::*;
 
@@ -12,6 +14,19 @@ use structures::{
     withdraw_tokens::*,
     buy_token::*,
     withdraw_sale_tokens::*,
+    update_merkle_root::*,
+    finalize_sale::*,
+    migrate_sale::*,
+    migrate_user_purchase::*,
+    cancel_sale::*,
+    pause_sale::*,
+    resume_sale::*,
+    change_admin::*,
+    init_config::*,
+    update_config::*,
+    set_allowed_mint::*,
+    close_user_purchase::*,
+    close_sale::*,
 };
 
 declare_id!("8u2V6SHBURgDV23rvWFKBvPvhthYKP3eHfYgGJzQHLps");
@@ -23,23 +38,112 @@ pub mod sell_token {
     // pub fn sale_account(ctx: Context<InitSaleAccount>, sale_amount: u64, price_per_token: u64, end_time: i64) -> Result<()> {
     //     ctx.accounts.process(sale_amount, price_per_token, end_time)
     // }
-    pub fn init_sale_account(ctx: Context<InitSaleAccount>, sale_amount: u64, price_per_token: u64, end_time: i64) -> Result<()> {
-        ctx.accounts.process(sale_amount, price_per_token, end_time)
+    pub fn init_config(ctx: Context<InitConfig>, admin: Pubkey, treasury: Pubkey, protocol_fee_bps: u16, sale_creation_fee: u64) -> Result<()> {
+        ctx.accounts.process(admin, treasury, protocol_fee_bps, sale_creation_fee)
     }
 
-    pub fn buy_token(ctx: Context<BuyToken>, amount: u64,open_time: u64) -> Result<()> {
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        admin: Option<Pubkey>,
+        treasury: Option<Pubkey>,
+        protocol_fee_bps: Option<u16>,
+        sale_creation_fee: Option<u64>,
+    ) -> Result<()> {
+        ctx.accounts.process(admin, treasury, protocol_fee_bps, sale_creation_fee)
+    }
+
+    pub fn set_allowed_mint(ctx: Context<SetAllowedMint>, min_price: u64, is_enabled: bool) -> Result<()> {
+        ctx.accounts.process(min_price, is_enabled)
+    }
+
+    pub fn init_sale_account(ctx: Context<InitSaleAccount>, params: InitSaleParams) -> Result<()> {
+        let event = ctx.accounts.process(params)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn update_merkle_root(ctx: Context<UpdateMerkleRoot>, merkle_root: Option<[u8; 32]>) -> Result<()> {
+        ctx.accounts.process(merkle_root)
+    }
+
+    pub fn buy_token(ctx: Context<BuyToken>, amount: u64, min_tokens_out: u64, allocation: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let event = ctx.accounts.process(amount, min_tokens_out, allocation, proof)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn buy_token_exact_out(ctx: Context<BuyToken>, token_amount: u64, max_payment: u64, allocation: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let event = ctx.accounts.process_exact_out(token_amount, max_payment, allocation, proof)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn finalize_sale(ctx: Context<FinalizeSale>) -> Result<()> {
         let bump = ctx.bumps.pda_account;
-        ctx.accounts.process(amount, bump,open_time)
+        let sol_vault_bump = ctx.bumps.sol_vault;
+        let event = ctx.accounts.process(bump, sol_vault_bump)?;
+        emit_cpi!(event);
+        Ok(())
     }
 
     pub fn withdraw_tokens(ctx: Context<WithdrawTokens>) -> Result<()> {
         let bump = ctx.bumps.pda_account;
-        ctx.accounts.process(bump)
+        let sol_vault_bump = ctx.bumps.sol_vault;
+        match ctx.accounts.process(bump, sol_vault_bump)? {
+            WithdrawTokensEvent::Claimed(event) => emit_cpi!(event),
+            WithdrawTokensEvent::Refunded(event) => emit_cpi!(event),
+        }
+        Ok(())
     }
 
     pub fn withdraw_sale_tokens(ctx: Context<WithdrawSaleTokens>) -> Result<()> {
         let bump = ctx.bumps.pda_account;
-        ctx.accounts.process(bump)
+        let sol_vault_bump = ctx.bumps.sol_vault;
+        let event = ctx.accounts.process(bump, sol_vault_bump)?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn cancel_sale(ctx: Context<CancelSale>) -> Result<()> {
+        let event = ctx.accounts.process()?;
+        emit_cpi!(event);
+        Ok(())
+    }
+
+    pub fn pause_sale(ctx: Context<PauseSale>) -> Result<()> {
+        ctx.accounts.process()
+    }
+
+    pub fn resume_sale(ctx: Context<ResumeSale>, extend_end_time: bool) -> Result<()> {
+        ctx.accounts.process(extend_end_time)
+    }
+
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+        ctx.accounts.process(new_owner)
+    }
+
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        ctx.accounts.process()
+    }
+
+    pub fn migrate_sale(ctx: Context<MigrateSale>) -> Result<()> {
+        let legacy_bump = ctx.bumps.legacy_pda_account;
+        ctx.accounts.process(legacy_bump)
+    }
+
+    pub fn migrate_user_purchase(ctx: Context<MigrateUserPurchase>) -> Result<()> {
+        let legacy_bump = ctx.bumps.legacy_pda_account;
+        ctx.accounts.process(legacy_bump)
+    }
+
+    pub fn close_user_purchase(ctx: Context<CloseUserPurchase>) -> Result<()> {
+        ctx.accounts.process()
+    }
+
+    pub fn close_sale(ctx: Context<CloseSale>) -> Result<()> {
+        let bump = ctx.bumps.pda_account;
+        let sol_vault_bump = ctx.bumps.sol_vault;
+        ctx.accounts.process(bump, sol_vault_bump)
     }
 }
 
@@ -0,0 +1,61 @@
+use anchor_lang::solana_program::keccak;
+
+/// 白名单叶子节点: keccak256(buyer || allocation)
+pub fn leaf(buyer: &[u8], allocation: u64) -> [u8; 32] {
+    keccak::hashv(&[buyer, allocation.to_le_bytes().as_ref()]).to_bytes()
+}
+
+/// 校验 merkle 证明，节点两两按字节序排序后再哈希
+pub fn verify(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let mut computed = leaf;
+    for node in proof.iter() {
+        computed = if computed <= *node {
+            keccak::hashv(&[computed.as_ref(), node.as_ref()]).to_bytes()
+        } else {
+            keccak::hashv(&[node.as_ref(), computed.as_ref()]).to_bytes()
+        };
+    }
+    computed == root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parent(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[a.as_ref(), b.as_ref()]).to_bytes()
+        } else {
+            keccak::hashv(&[b.as_ref(), a.as_ref()]).to_bytes()
+        }
+    }
+
+    #[test]
+    fn single_leaf_tree_needs_no_proof() {
+        let leaf_a = leaf(&[1u8; 32], 100);
+        assert!(verify(&[], leaf_a, leaf_a));
+    }
+
+    #[test]
+    fn verifies_every_leaf_of_a_four_leaf_tree() {
+        let leaves: Vec<[u8; 32]> = (0..4u8).map(|i| leaf(&[i; 32], 100 * i as u64)).collect();
+        let left = parent(leaves[0], leaves[1]);
+        let right = parent(leaves[2], leaves[3]);
+        let root = parent(left, right);
+
+        assert!(verify(&[leaves[1], right], root, leaves[0]));
+        assert!(verify(&[leaves[0], right], root, leaves[1]));
+        assert!(verify(&[leaves[3], left], root, leaves[2]));
+        assert!(verify(&[leaves[2], left], root, leaves[3]));
+    }
+
+    #[test]
+    fn rejects_wrong_allocation_and_bad_proof() {
+        let leaves: Vec<[u8; 32]> = (0..2u8).map(|i| leaf(&[i; 32], 100)).collect();
+        let root = parent(leaves[0], leaves[1]);
+
+        assert!(!verify(&[leaves[1]], root, leaf(&[0u8; 32], 101)));
+        assert!(!verify(&[leaves[0]], root, leaves[0]));
+        assert!(!verify(&[], root, leaves[0]));
+    }
+}
@@ -1,23 +1,19 @@
 use super::SaleAccount;
+use super::SaleStatus;
 use super::UserPurchase;
 use anchor_lang::prelude::*;
+use anchor_lang::system_program;
 
-use anchor_spl::{
-    associated_token::AssociatedToken,
-    token::{Token,Mint,TokenAccount},
-    token_interface::{ TokenInterface,Transfer,transfer},
-};
-
-use raydium_cp_swap::{
-    cpi,
-    program::RaydiumCpSwap,
-    states::{AmmConfig, OBSERVATION_SEED, POOL_LP_MINT_SEED, POOL_SEED, POOL_VAULT_SEED},
-};
+use anchor_spl::token_2022::{self, spl_token_2022};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
 
 use super::error::ErrorCode;
+use crate::events::TokensPurchased;
 
+#[event_cpi]
 #[derive(Accounts)]
-#[instruction(bump: u8)]
 pub struct BuyToken<'info> {
     #[account(
         mut,
@@ -28,275 +24,330 @@ pub struct BuyToken<'info> {
 /// CHECK:` doc comment explaining why no checks through types are necessary.
     #[account(
         mut,
-        seeds = [crate::TOKEN_SEED], 
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()], 
         bump,
     )]
-    pub pda_account: AccountInfo<'info>, //合约pda账户
-    
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
     #[account(
         mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+    
+    #[account(
         constraint = token_mint.key() == sale.token_mint,
     )]
-    pub token_mint: Box<Account<'info, Mint>>,
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
     
     #[account(
-        mut,
         constraint = buy_token_mint.key() == sale.buy_token_mint,
+        mint::token_program = buy_token_program,
     )]
-    pub buy_token_mint: Box<Account<'info, Mint>>,
+    pub buy_token_mint: Box<InterfaceAccount<'info, Mint>>,
     
     #[account(mut)]
     pub buyer: Signer<'info>,
     
+    /// 原生 SOL 支付时不需要
     #[account(
         mut,
         token::mint = buy_token_mint,
         token::authority = buyer,
+        token::token_program = buy_token_program,
     )]
-    pub buyer_token_account: Box<Account<'info, TokenAccount>>,
+    pub buyer_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     
+    /// 原生 SOL 支付时不需要
     #[account(
         mut,
         token::mint = buy_token_mint,
         token::authority = pda_account,
+        token::token_program = buy_token_program,
     )]
-    pub sale_token_account: Box<Account<'info, TokenAccount>>,
+    pub sale_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
     
     #[account(
-        mut,
-        constraint = sale_sell_token_account.owner == pda_account.key(),
-        constraint = sale_sell_token_account.mint == token_mint.key()
-    )]
-    pub sale_sell_token_account: Account<'info, TokenAccount>,
-
-    // Raydium accounts
-    pub cp_swap_program: Program<'info, RaydiumCpSwap>,
-    
-    pub amm_config: Box<Account<'info, AmmConfig>>,
-
-    /// CHECK: Authority is a PDA owned by Raydium
-    #[account(
-        seeds = [
-            raydium_cp_swap::AUTH_SEED.as_bytes(),
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub authority: UncheckedAccount<'info>,
-
-    /// CHECK: Pool state is initialized by Raydium
-    #[account(
-        mut,
-        seeds = [
-            POOL_SEED.as_bytes(),
-            amm_config.key().as_ref(),
-            token_mint.key().as_ref(),
-            buy_token_mint.key().as_ref(),
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub pool_state: UncheckedAccount<'info>,
-
-    /// CHECK: LP mint is initialized by Raydium
-    #[account(
-        mut,
-        seeds = [
-            POOL_LP_MINT_SEED.as_bytes(),
-            pool_state.key().as_ref(),
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub lp_mint: UncheckedAccount<'info>,
-
-    /// CHECK: Creator LP token account is initialized by Raydium
-    #[account(mut)]
-    pub creator_lp_token: UncheckedAccount<'info>,
-
-    /// CHECK: Token vaults are initialized by Raydium
-    #[account(
-        mut,
-        seeds = [
-            POOL_VAULT_SEED.as_bytes(),
-            pool_state.key().as_ref(),
-            token_mint.key().as_ref()
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub token_0_vault: UncheckedAccount<'info>,
-
-     /// CHECK: Token vaults are initialized by Raydium
-    #[account(
-        mut,
-        seeds = [
-            POOL_VAULT_SEED.as_bytes(),
-            pool_state.key().as_ref(),
-            buy_token_mint.key().as_ref()
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub token_1_vault: UncheckedAccount<'info>,
-
-    #[account(
-        mut,
-        address= raydium_cp_swap::create_pool_fee_reveiver::ID,
-    )]
-    pub create_pool_fee: Box<Account<'info, TokenAccount>>,
-
-    /// CHECK: Observation state is initialized by Raydium
-    #[account(
-        mut,
-        seeds = [
-            OBSERVATION_SEED.as_bytes(),
-            pool_state.key().as_ref(),
-        ],
-        seeds::program = cp_swap_program.key(),
-        bump,
-    )]
-    pub observation_state: UncheckedAccount<'info>,
-
-    #[account(
-        init,
+        init_if_needed,
         payer = buyer,
         space = 8 + core::mem::size_of::<UserPurchase>(),
         seeds = [crate::TOKEN_PURCHASE, buyer.key().as_ref(), token_mint.key().as_ref()],
         bump
     )]
     pub user_purchase: Account<'info, UserPurchase>,
-    pub token_program: Program<'info, Token>,
-    /// Spl token program or token program 2022
-    pub token_0_program: Interface<'info, TokenInterface>,
-    /// Spl token program or token program 2022
-    pub token_1_program: Interface<'info, TokenInterface>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 impl<'info> BuyToken<'info> {
-    pub fn process(&mut self, amount: u64, bump_seed: u8, open_time: u64) -> Result<()> {
+    /// 按支付金额购买，实际成交数量低于 min_tokens_out 时失败
+    pub fn process(&mut self, amount: u64, min_tokens_out: u64, allocation: u64, proof: Vec<[u8; 32]>) -> Result<TokensPurchased> {
+        let current_time = self.validate_purchase(allocation, &proof)?;
+
+        // 按当前分数价格换算销售代币最小单位数量，不足一个最小单位的部分不收取
+        let token_amount = self.sale.tokens_for_payment(amount, current_time)?;
+
+        self.purchase(token_amount, min_tokens_out, amount, false, allocation, current_time)
+    }
+
+    /// 按购买数量购买，数量必须全部成交，且支付金额（含支付代币转账手续费）不能超过 max_payment
+    pub fn process_exact_out(&mut self, token_amount: u64, max_payment: u64, allocation: u64, proof: Vec<[u8; 32]>) -> Result<TokensPurchased> {
+        let current_time = self.validate_purchase(allocation, &proof)?;
+
+        self.purchase(token_amount, token_amount, max_payment, true, allocation, current_time)
+    }
+
+    /// 校验销售状态和白名单，返回当前时间
+    fn validate_purchase(&mut self, allocation: u64, proof: &[[u8; 32]]) -> Result<i64> {
         let current_time = Clock::get()?.unix_timestamp;
-        
-        if current_time > self.sale.end_time {
-            return Err(ErrorCode::SaleEnded.into());
+
+        // 只有 Live 状态可以购买
+        match self.sale.refresh_status(current_time)? {
+            SaleStatus::Live => {}
+            SaleStatus::Upcoming => return Err(ErrorCode::SaleNotStarted.into()),
+            SaleStatus::Paused => return Err(ErrorCode::SalePaused.into()),
+            SaleStatus::Cancelled => return Err(ErrorCode::SaleCancelled.into()),
+            SaleStatus::SoldOut => return Err(ErrorCode::NoTokensLeft.into()),
+            _ => return Err(ErrorCode::SaleEnded.into()),
         }
 
-        if self.sale.remaining_amount == 0 {
-            return Err(ErrorCode::NoTokensLeft.into());
+        // 白名单校验，叶子节点为 hash(buyer, allocation)
+        if self.sale.is_whitelisted() {
+            let leaf = crate::merkle::leaf(self.buyer.key().as_ref(), allocation);
+            if !crate::merkle::verify(proof, self.sale.merkle_root, leaf) {
+                msg!("Buyer is not whitelisted.");
+                return Err(ErrorCode::InvalidMerkleProof.into());
+            }
         }
-        
-        let token_amount = amount.checked_div(self.sale.price_per_token)
-            .ok_or(ErrorCode::Overflow)?;
-            
+
+        Ok(current_time)
+    }
+
+    /// 购买 token_amount 个销售代币最小单位，超出剩余数量的部分自动截断
+    /// 截断及扣除转账手续费后的成交数量低于 min_tokens_out，或应付金额高于 max_payment 时失败
+    /// include_transfer_fee 为 true 时买家额外承担支付代币的转账手续费，金库按应付金额足额到账
+    fn purchase(
+        &mut self,
+        token_amount: u64,
+        min_tokens_out: u64,
+        max_payment: u64,
+        include_transfer_fee: bool,
+        allocation: u64,
+        current_time: i64,
+    ) -> Result<TokensPurchased> {
+        let token_amount = std::cmp::min(token_amount, self.sale.remaining_amount);
+
         if token_amount == 0 {
             return Err(ErrorCode::AmountTooSmall.into());
         }
 
-        let decimals = 10u128
-            .checked_pow(self.token_mint.decimals.into())
-            .ok_or(ErrorCode::Overflow)?;
-    
-        let token_amount_u128 = (token_amount as u128)
-            .checked_mul(decimals)
+        if token_amount < min_tokens_out {
+            msg!("Fill of {} tokens below minimum {}.", token_amount, min_tokens_out);
+            return Err(ErrorCode::TokensOutBelowMinimum.into());
+        }
+
+        // 只收取实际购买数量对应的金额，多余的零头留在买家钱包
+        let actual_amount = self.sale.payment_for_tokens(token_amount, current_time)?;
+
+        let transfer_amount = if include_transfer_fee {
+            actual_amount
+                .checked_add(self.payment_transfer_fee(actual_amount)?)
+                .ok_or(ErrorCode::Overflow)?
+        } else {
+            actual_amount
+        };
+
+        if transfer_amount > max_payment {
+            msg!("Payment of {} above maximum {}.", transfer_amount, max_payment);
+            return Err(ErrorCode::PaymentAboveMaximum.into());
+        }
+
+        let received_amount = self.collect_payment(transfer_amount)?;
+
+        let actual_token_amount = if received_amount < actual_amount {
+            std::cmp::min(self.sale.tokens_for_payment(received_amount, current_time)?, token_amount)
+        } else {
+            token_amount
+        };
+
+        if actual_token_amount == 0 {
+            return Err(ErrorCode::AmountTooSmall.into());
+        }
+
+        // 支付代币转账手续费会减少成交数量，需再次校验
+        if actual_token_amount < min_tokens_out {
+            msg!("Fill of {} tokens below minimum {}.", actual_token_amount, min_tokens_out);
+            return Err(ErrorCode::TokensOutBelowMinimum.into());
+        }
+
+        // 同一代币重新创建销售时购买记录地址不变，上一轮已结算的记录清零后按首次购买处理
+        if self.user_purchase.is_claim {
+            self.user_purchase.user_address = Pubkey::default();
+            self.user_purchase.token_amount = 0;
+            self.user_purchase.purchase_amount = 0;
+            self.user_purchase.purchase_count = 0;
+            self.user_purchase.claimed_amount = 0;
+            self.user_purchase.weighted_price_numerator = 0;
+        }
+
+        // 累计用户购买记录，后续的钱包限额都基于累计值校验
+        let is_first_purchase = self.user_purchase.user_address == Pubkey::default();
+
+        let total_token_amount = self.user_purchase.token_amount
+            .checked_add(actual_token_amount)
             .ok_or(ErrorCode::Overflow)?;
-    
-        let actual_token_amount = std::cmp::min(token_amount_u128, self.sale.remaining_amount as u128);
-    
-        let amount = actual_token_amount
-            .checked_mul(self.sale.price_per_token as u128)
-            .ok_or(ErrorCode::Overflow)?
-            .checked_div(decimals)
+
+        let total_purchase_amount = self.user_purchase.purchase_amount
+            .checked_add(received_amount)
             .ok_or(ErrorCode::Overflow)?;
 
-        let actual_amount = u64::try_from(amount)
-            .map_err(|_| ErrorCode::Overflow)?;
+        // 校验单个钱包的累计购买金额
+        if self.sale.min_contribution > 0 && total_purchase_amount < self.sale.min_contribution {
+            msg!("Contribution below minimum.");
+            return Err(ErrorCode::ContributionBelowMinimum.into());
+        }
+
+        if self.sale.max_contribution > 0 && total_purchase_amount > self.sale.max_contribution {
+            msg!("Contribution above maximum.");
+            return Err(ErrorCode::ContributionAboveMaximum.into());
+        }
 
-        transfer(
-            self.into_transfer_to_buyer_context(),
-            actual_amount
-        )?;
+        // 白名单额度限制累计购买金额，0 表示不限制
+        if self.sale.is_whitelisted() && allocation > 0 && total_purchase_amount > allocation {
+            msg!("Whitelist allocation exceeded.");
+            return Err(ErrorCode::AllocationExceeded.into());
+        }
+
+        let purchase_count = self.user_purchase.purchase_count
+            .checked_add(1)
+            .ok_or(ErrorCode::Overflow)?;
 
         self.sale.remaining_amount = self.sale.remaining_amount
-            .checked_sub(actual_token_amount as u64)
+            .checked_sub(actual_token_amount)
             .ok_or(ErrorCode::Overflow)?;
 
-        if self.sale.remaining_amount == 0 {
-            self.sale.is_active = false;
+        self.sale.sold_amount = self.sale.sold_amount
+            .checked_add(actual_token_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        self.sale.total_raised = self.sale.total_raised
+            .checked_add(received_amount)
+            .ok_or(ErrorCode::Overflow)?;
 
-            // Add liquidity to Raydium
-            //amount_0 为代币总量减去sale_amount
-            
-            let amount_0 = self.token_mint.supply
-                .checked_sub(self.sale.sale_amount as u64)
+        if is_first_purchase {
+            self.user_purchase.user_address = self.buyer.key();
+            self.user_purchase.token_address = self.token_mint.key();
+            self.user_purchase.purchase_time = current_time;
+            self.user_purchase.is_claim = false;
+            self.sale.unsettled_purchases = self.sale.unsettled_purchases
+                .checked_add(1)
                 .ok_or(ErrorCode::Overflow)?;
-                
-            let amount_1 = self.sale_token_account.amount as u64;
-
-            let cpi_accounts = cpi::accounts::Initialize {
-                creator: self.pda_account.to_account_info(),
-                amm_config: self.amm_config.to_account_info(),
-                authority: self.authority.to_account_info(),
-                pool_state: self.pool_state.to_account_info(),
-                token_0_mint: self.token_mint.to_account_info(),
-                token_1_mint: self.buy_token_mint.to_account_info(),
-                lp_mint: self.lp_mint.to_account_info(),
-                creator_token_0: self.sale_sell_token_account.to_account_info(),
-                creator_token_1: self.sale_token_account.to_account_info(),
-                creator_lp_token: self.creator_lp_token.to_account_info(),
-                token_0_vault: self.token_0_vault.to_account_info(),
-                token_1_vault: self.token_1_vault.to_account_info(),
-                create_pool_fee: self.create_pool_fee.to_account_info(),
-                observation_state: self.observation_state.to_account_info(),
-                token_program: self.token_program.to_account_info(),
-                token_0_program: self.token_0_program.to_account_info(),
-                token_1_program: self.token_1_program.to_account_info(),
-                associated_token_program: self.associated_token_program.to_account_info(),
-                system_program: self.system_program.to_account_info(),
-                rent: self.rent.to_account_info(),
-            };
-
-            let signer_seeds: &[&[&[u8]]] = &[&[
-                crate::TOKEN_SEED,  
-                &[bump_seed]
-            ]];
-
-            let cpi_ctx = CpiContext::new_with_signer(
-                self.cp_swap_program.to_account_info(),
-                cpi_accounts,
-                signer_seeds
-            );
-
-            cpi::initialize(cpi_ctx, amount_0, amount_1, open_time)?;
         }
 
-        msg!("Bought {} tokens for {} lamports", actual_token_amount, actual_amount);
+        self.user_purchase.token_amount = total_token_amount;
+        let price_numerator = self.sale.current_price_numerator(current_time)?;
+        self.user_purchase.record_price(actual_token_amount, price_numerator, self.sale.price_denominator)?;
+        self.user_purchase.purchase_amount = total_purchase_amount;
+        self.user_purchase.purchase_count = purchase_count;
+        self.user_purchase.last_purchase_time = current_time;
 
-        if self.user_purchase.user_address == self.buyer.key() {
-            return Err(ErrorCode::UserAlreadyPurchased.into());
-        }   
+        // 售罄后停止购买，流动性由 finalize_sale 注入
+        if self.sale.remaining_amount == 0 {
+            self.sale.transition(SaleStatus::SoldOut)?;
+        }
 
-        self.user_purchase.user_address = self.buyer.key();
-        self.user_purchase.token_amount = actual_token_amount as u64;
-        self.user_purchase.token_price = self.sale.price_per_token;
-        self.user_purchase.token_address = self.token_mint.key();
-        self.user_purchase.purchase_amount = actual_amount;
-        self.user_purchase.purchase_time = current_time;
-        self.user_purchase.is_claim = false;
+        msg!("Bought {} tokens for {} lamports", actual_token_amount, received_amount);
 
-        Ok(())
+        Ok(TokensPurchased {
+            sale: self.sale.key(),
+            buyer: self.buyer.key(),
+            token_mint: self.token_mint.key(),
+            buy_token_mint: self.buy_token_mint.key(),
+            token_amount: actual_token_amount,
+            payment_amount: received_amount,
+            price_numerator,
+            price_denominator: self.sale.price_denominator,
+            remaining_amount: self.sale.remaining_amount,
+            timestamp: current_time,
+        })
     }
 
-    pub fn into_transfer_to_buyer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        CpiContext::new(
-            self.token_program.to_account_info(),
-            Transfer {
-                from: self.buyer_token_account.to_account_info(),
-                to: self.sale_token_account.to_account_info(),
+    /// Token-2022 支付代币带转账手续费时，返回使金库实际到账 amount 需要额外支付的手续费
+    fn payment_transfer_fee(&self, amount: u64) -> Result<u64> {
+        if self.sale.native_payment || self.buy_token_program.key() != token_2022::ID {
+            return Ok(0);
+        }
+
+        let mint_info = self.buy_token_mint.to_account_info();
+        let data = mint_info.try_borrow_data()?;
+        let mint = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&data)?;
+
+        match mint.get_extension::<TransferFeeConfig>() {
+            Ok(fee_config) => Ok(fee_config
+                .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+                .ok_or(ErrorCode::Overflow)?),
+            Err(_) => Ok(0),
+        }
+    }
+
+    /// 收取买家付款，返回金库实际到账数量
+    fn collect_payment(&mut self, amount: u64) -> Result<u64> {
+        if self.sale.native_payment {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.buyer.to_account_info(),
+                        to: self.sol_vault.to_account_info(),
+                    },
+                ),
+                amount,
+            )?;
+            return Ok(amount);
+        }
+
+        let sale_balance_before = self.sale_token_account
+            .as_ref()
+            .ok_or(ErrorCode::MissingTokenAccount)?
+            .amount;
+
+        transfer_checked(
+            self.into_transfer_to_buyer_context()?,
+            amount,
+            self.buy_token_mint.decimals,
+        )?;
+
+        // 支付代币可能带转账手续费，以金库实际到账数量记账
+        let sale_token_account = self.sale_token_account
+            .as_mut()
+            .ok_or(ErrorCode::MissingTokenAccount)?;
+        sale_token_account.reload()?;
+        let received_amount = sale_token_account.amount
+            .checked_sub(sale_balance_before)
+            .ok_or(ErrorCode::Overflow)?;
+
+        Ok(received_amount)
+    }
+
+    pub fn into_transfer_to_buyer_context(&self) -> Result<CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>> {
+        let buyer_token_account = self.buyer_token_account
+            .as_ref()
+            .ok_or(ErrorCode::MissingTokenAccount)?;
+        let sale_token_account = self.sale_token_account
+            .as_ref()
+            .ok_or(ErrorCode::MissingTokenAccount)?;
+
+        Ok(CpiContext::new(
+            self.buy_token_program.to_account_info(),
+            TransferChecked {
+                from: buyer_token_account.to_account_info(),
+                mint: self.buy_token_mint.to_account_info(),
+                to: sale_token_account.to_account_info(),
                 authority: self.buyer.to_account_info(),
             },
-        )
+        ))
     }
 } 
\ No newline at end of file
@@ -0,0 +1,48 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+use crate::events::SaleCancelled;
+
+/// 所有者取消销售，取消后停止购买，买家可立即退款
+#[event_cpi]
+#[derive(Accounts)]
+pub struct CancelSale<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        constraint = sale.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+impl<'info> CancelSale<'info> {
+    pub fn process(&mut self) -> Result<SaleCancelled> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 只有结果判定前（Upcoming、Live、Paused）可以取消
+        self.sale.refresh_status(current_time)?;
+        self.sale.transition(SaleStatus::Cancelled)?;
+
+        msg!("Sale cancelled");
+
+        Ok(SaleCancelled {
+            sale: self.sale.key(),
+            owner: self.owner.key(),
+            token_mint: self.token_mint.key(),
+            sold_amount: self.sale.sold_amount,
+            total_raised: self.sale.total_raised,
+            timestamp: current_time,
+        })
+    }
+}
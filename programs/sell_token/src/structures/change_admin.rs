@@ -0,0 +1,88 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+
+/// 所有权转移在关闭前的任何状态均可进行，所有者需要在结算后继续提取资金
+fn require_not_closed(sale: &mut SaleAccount, now: i64) -> Result<()> {
+    if sale.refresh_status(now)? == SaleStatus::Closed {
+        msg!("Sale closed.");
+        return Err(ErrorCode::SaleClosed.into());
+    }
+
+    Ok(())
+}
+
+/// 所有权转移第一步：当前所有者提名新所有者
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        constraint = sale.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+impl<'info> ProposeOwner<'info> {
+    /// 传入 Pubkey::default() 可撤销提名
+    pub fn process(&mut self, new_owner: Pubkey) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require_not_closed(&mut self.sale, current_time)?;
+
+        self.sale.pending_owner = new_owner;
+
+        msg!("Proposed new owner {}", new_owner);
+
+        Ok(())
+    }
+}
+
+/// 所有权转移第二步：被提名者签名接受
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub new_owner: Signer<'info>,
+}
+
+impl<'info> AcceptOwner<'info> {
+    pub fn process(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+        require_not_closed(&mut self.sale, current_time)?;
+
+        if self.sale.pending_owner == Pubkey::default()
+            || self.sale.pending_owner != self.new_owner.key()
+        {
+            msg!("Signer is not the pending owner.");
+            return Err(ErrorCode::Unauthorized.into());
+        }
+
+        self.sale.owner = self.new_owner.key();
+        self.sale.pending_owner = Pubkey::default();
+
+        msg!("Ownership transferred to {}", self.sale.owner);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,263 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_spl::token_2022::{self, spl_token_2022};
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeAmount;
+use anchor_spl::token_2022_extensions::{harvest_withheld_tokens_to_mint, HarvestWithheldTokensToMint};
+use anchor_spl::token_interface::{close_account, CloseAccount, Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
+
+use super::error::ErrorCode;
+
+/// 所有者在全部购买记录结算且提取完应得部分后关闭销售，取回销售账户和金库的租金
+/// 金库中记账之外的余额（他人转入的零头）一并转给所有者
+#[derive(Accounts)]
+pub struct CloseSale<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        has_one = token_mint,
+        has_one = buy_token_mint,
+    )]
+    pub sale: Box<Account<'info, SaleAccount>>,
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        mut,
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()],
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
+    #[account(
+        mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    /// Token-2022 归集扣留的转账手续费时需要可写
+    #[account(
+        mut,
+        mint::token_program = token_program,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        mint::token_program = buy_token_program,
+    )]
+    pub buy_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// 销售代币金库
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = token_program,
+    )]
+    pub sale_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 支付代币金库
+    #[account(
+        mut,
+        associated_token::mint = buy_token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = buy_token_program,
+    )]
+    pub contract_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 所有者接收剩余销售代币的账户，销售代币金库为空时不需要
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = owner,
+        token::token_program = token_program,
+    )]
+    pub owner_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    /// 所有者接收剩余支付代币的账户，支付代币金库为空时不需要
+    #[account(
+        mut,
+        token::mint = buy_token_mint,
+        token::authority = owner,
+        token::token_program = buy_token_program,
+    )]
+    pub owner_buy_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    pub system_program: Program<'info, System>,
+    /// 销售代币所属的 token program
+    pub token_program: Interface<'info, TokenInterface>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> CloseSale<'info> {
+    pub fn process(&mut self, bump_seed: u8, sol_vault_bump: u8) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 只有失败、取消或已注入流动性的销售可以关闭
+        let status = self.sale.refresh_status(current_time)?;
+        self.sale.transition(SaleStatus::Closed)?;
+
+        if self.sale.unsettled_purchases > 0 {
+            msg!("{} purchases not settled.", self.sale.unsettled_purchases);
+            return Err(ErrorCode::PurchaseNotSettled.into());
+        }
+
+        // 未迁移的旧版本购买记录需要销售账户才能迁移和退款
+        if self.sale.unmigrated_amount > 0 {
+            msg!("{} legacy tokens not migrated.", self.sale.unmigrated_amount);
+            return Err(ErrorCode::PurchasesNotMigrated.into());
+        }
+
+        // 以记账数值判断所有者是否已提取应得部分，金库实际余额可能被他人转入零头
+        let owner_withdrawn = if status == SaleStatus::LiquiditySeeded {
+            self.sale.remaining_amount == 0
+                && self.sale.proceeds_withdrawn >= self.sale.owner_proceeds()?
+        } else {
+            self.sale.unsold_withdrawn >= self.sale.deposited_amount
+        };
+
+        if !owner_withdrawn {
+            msg!("Owner funds not withdrawn.");
+            return Err(ErrorCode::VaultNotEmpty.into());
+        }
+
+        let sale_key = self.sale.key();
+
+        // SOL 金库中的全部 lamports（含预存的免租金额和他人转入的零头）退还给所有者
+        let sol_vault_balance = self.sol_vault.lamports();
+        if sol_vault_balance > 0 {
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::SOL_VAULT, sale_key.as_ref(), &[sol_vault_bump]]];
+
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.sol_vault.to_account_info(),
+                        to: self.owner.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                sol_vault_balance,
+            )?;
+        }
+
+        // 清空并关闭两个金库代币账户，租金退还给所有者
+        let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
+
+        Self::close_vault(
+            &self.token_program,
+            &self.token_mint,
+            &self.sale_token_account,
+            self.owner_token_account.as_deref(),
+            &self.owner,
+            &self.pda_account,
+            signer_seeds,
+        )?;
+
+        Self::close_vault(
+            &self.buy_token_program,
+            &self.buy_token_mint,
+            &self.contract_token_account,
+            self.owner_buy_token_account.as_deref(),
+            &self.owner,
+            &self.pda_account,
+            signer_seeds,
+        )?;
+
+        // 金库 pda 中剩余的 lamports 一并退还给所有者
+        let pda_balance = self.pda_account.lamports();
+        if pda_balance > 0 {
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.pda_account.to_account_info(),
+                        to: self.owner.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                pda_balance,
+            )?;
+        }
+
+        msg!("Closed sale {}", sale_key);
+
+        Ok(())
+    }
+
+    /// 把金库剩余余额转给所有者，归集 Token-2022 扣留的转账手续费后关闭金库
+    fn close_vault(
+        token_program: &Interface<'info, TokenInterface>,
+        mint: &InterfaceAccount<'info, Mint>,
+        vault: &InterfaceAccount<'info, TokenAccount>,
+        destination: Option<&InterfaceAccount<'info, TokenAccount>>,
+        owner: &Signer<'info>,
+        authority: &AccountInfo<'info>,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        if vault.amount > 0 {
+            let destination = destination.ok_or(ErrorCode::MissingTokenAccount)?;
+
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program.to_account_info(),
+                    TransferChecked {
+                        from: vault.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: destination.to_account_info(),
+                        authority: authority.clone(),
+                    },
+                    signer_seeds,
+                ),
+                vault.amount,
+                mint.decimals,
+            )?;
+        }
+
+        // Token-2022 账户中扣留的转账手续费不为零时无法关闭，先归集到 mint
+        if token_program.key() == token_2022::ID {
+            let withheld_amount = {
+                let vault_info = vault.to_account_info();
+                let data = vault_info.try_borrow_data()?;
+                let state = StateWithExtensions::<spl_token_2022::state::Account>::unpack(&data)?;
+                state.get_extension::<TransferFeeAmount>()
+                    .map(|fee_amount| u64::from(fee_amount.withheld_amount))
+                    .unwrap_or(0)
+            };
+
+            if withheld_amount > 0 {
+                harvest_withheld_tokens_to_mint(
+                    CpiContext::new(
+                        token_program.to_account_info(),
+                        HarvestWithheldTokensToMint {
+                            token_program_id: token_program.to_account_info(),
+                            mint: mint.to_account_info(),
+                        },
+                    ),
+                    vec![vault.to_account_info()],
+                )?;
+            }
+        }
+
+        close_account(CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            CloseAccount {
+                account: vault.to_account_info(),
+                destination: owner.to_account_info(),
+                authority: authority.clone(),
+            },
+            signer_seeds,
+        ))
+    }
+}
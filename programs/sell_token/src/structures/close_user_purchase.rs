@@ -0,0 +1,34 @@
+use super::UserPurchase;
+use anchor_lang::prelude::*;
+
+use super::error::ErrorCode;
+
+/// 买家在退款或全部领取后关闭购买记录，取回租金
+#[derive(Accounts)]
+pub struct CloseUserPurchase<'info> {
+    #[account(
+        mut,
+        close = buyer,
+        constraint = user_purchase.user_address == buyer.key() @ ErrorCode::Unauthorized,
+        seeds = [crate::TOKEN_PURCHASE, buyer.key().as_ref(), user_purchase.token_address.as_ref()],
+        bump
+    )]
+    pub user_purchase: Account<'info, UserPurchase>,
+
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+}
+
+impl<'info> CloseUserPurchase<'info> {
+    pub fn process(&mut self) -> Result<()> {
+        // 只有已结算的购买记录可以关闭
+        if !self.user_purchase.is_claim {
+            msg!("Purchase not settled.");
+            return Err(ErrorCode::PurchaseNotSettled.into());
+        }
+
+        msg!("Closed purchase record of {}", self.buyer.key());
+
+        Ok(())
+    }
+}
@@ -67,6 +67,103 @@ pub enum ErrorCode {
 
     #[msg("MissingRequiredSignature.")]
     MissingRequiredSignature,
-    
-}
 
+    #[msg("Contribution is below the per-wallet minimum.")]
+    ContributionBelowMinimum,
+
+    #[msg("Contribution exceeds the per-wallet maximum.")]
+    ContributionAboveMaximum,
+
+    #[msg("Invalid contribution limits.")]
+    InvalidContributionLimits,
+
+    #[msg("Sale not started.")]
+    SaleNotStarted,
+
+    #[msg("Invalid start time.")]
+    InvalidStartTime,
+
+    #[msg("Sale already started.")]
+    SaleAlreadyStarted,
+
+    #[msg("Invalid merkle proof.")]
+    InvalidMerkleProof,
+
+    #[msg("Whitelist allocation exceeded.")]
+    AllocationExceeded,
+
+    #[msg("Native payment requires the wrapped SOL mint.")]
+    InvalidPaymentMint,
+
+    #[msg("Required token account is missing.")]
+    MissingTokenAccount,
+
+    #[msg("Invalid soft cap.")]
+    InvalidSoftCap,
+
+    #[msg("Sale did not reach the soft cap.")]
+    SoftCapNotReached,
+
+    #[msg("Pool already seeded.")]
+    PoolAlreadySeeded,
+
+    #[msg("Pool not seeded.")]
+    PoolNotSeeded,
+
+    #[msg("Invalid pool open time.")]
+    InvalidOpenTime,
+
+    #[msg("Invalid liquidity bps.")]
+    InvalidLiquidityBps,
+
+    #[msg("Insufficient tokens reserved for liquidity.")]
+    InsufficientLiquidityTokens,
+
+    #[msg("Account already migrated.")]
+    AlreadyMigrated,
+
+    #[msg("Legacy purchases not migrated.")]
+    PurchasesNotMigrated,
+
+    #[msg("Invalid vesting schedule.")]
+    InvalidVestingSchedule,
+
+    #[msg("Sale cancelled.")]
+    SaleCancelled,
+
+    #[msg("Sale paused.")]
+    SalePaused,
+
+    #[msg("Sale not paused.")]
+    SaleNotPaused,
+
+    #[msg("Invalid fee.")]
+    InvalidFee,
+
+    #[msg("Payment mint not allowed.")]
+    PaymentMintNotAllowed,
+
+    #[msg("Price below the minimum for this payment mint.")]
+    PriceBelowMinimum,
+
+    #[msg("Token amount below the minimum requested.")]
+    TokensOutBelowMinimum,
+
+    #[msg("Payment amount above the maximum allowed.")]
+    PaymentAboveMaximum,
+
+    #[msg("Purchase already settled.")]
+    AlreadyClaimed,
+
+    #[msg("Invalid sale status transition.")]
+    InvalidStatusTransition,
+
+    #[msg("Sale closed.")]
+    SaleClosed,
+
+    #[msg("Purchase not settled.")]
+    PurchaseNotSettled,
+
+    #[msg("Vault not empty.")]
+    VaultNotEmpty,
+}
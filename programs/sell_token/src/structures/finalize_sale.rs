@@ -0,0 +1,457 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use super::GlobalConfig;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token::Token,
+    token_interface::{Mint, TokenAccount, TokenInterface, SyncNative, sync_native, TransferChecked, transfer_checked},
+};
+
+use raydium_cp_swap::{
+    cpi,
+    program::RaydiumCpSwap,
+    states::{AmmConfig, OBSERVATION_SEED, POOL_LP_MINT_SEED, POOL_SEED, POOL_VAULT_SEED},
+};
+
+use super::error::ErrorCode;
+use crate::events::PoolSeeded;
+
+/// 销售成功后创建 Raydium CPMM 池子，任何人都可以调用
+#[event_cpi]
+#[derive(Accounts)]
+pub struct FinalizeSale<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump
+    )]
+    pub sale: Box<Account<'info, SaleAccount>>,
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        mut,
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()], 
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立，作为池子创建者支付 Raydium 账户租金
+
+    /// 调用者，为池子创建者垫付租金和创建池子手续费，创建后退还剩余部分
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
+    #[account(
+        mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: SystemAccount<'info>,
+
+    #[account(
+        seeds = [crate::GLOBAL_CONFIG],
+        bump,
+    )]
+    pub config: Box<Account<'info, GlobalConfig>>,
+
+    /// 手续费接收地址，原生 SOL 支付时直接接收 lamports
+    #[account(
+        mut,
+        address = config.treasury,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// 手续费接收地址的支付代币账户，原生 SOL 支付时不需要
+    #[account(
+        mut,
+        token::mint = buy_token_mint,
+        token::authority = treasury,
+        token::token_program = token_1_program,
+    )]
+    pub treasury_token_account: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+
+    #[account(
+        mut,
+        constraint = token_mint.key() == sale.token_mint,
+        mint::token_program = token_0_program,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mut,
+        constraint = buy_token_mint.key() == sale.buy_token_mint,
+        mint::token_program = token_1_program,
+    )]
+    pub buy_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// 合约的销售代币账户
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = pda_account,
+        token::token_program = token_0_program,
+    )]
+    pub sale_sell_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 合约的支付代币账户，原生 SOL 支付时为 wSOL 账户
+    #[account(
+        mut,
+        token::mint = buy_token_mint,
+        token::authority = pda_account,
+        token::token_program = token_1_program,
+    )]
+    pub sale_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    // Raydium accounts
+    pub cp_swap_program: Program<'info, RaydiumCpSwap>,
+    
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// CHECK: Authority is a PDA owned by Raydium
+    #[account(
+        seeds = [
+            raydium_cp_swap::AUTH_SEED.as_bytes(),
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub authority: UncheckedAccount<'info>,
+
+    /// CHECK: Pool state is initialized by Raydium
+    #[account(
+        mut,
+        seeds = [
+            POOL_SEED.as_bytes(),
+            amm_config.key().as_ref(),
+            token_mint.key().as_ref(),
+            buy_token_mint.key().as_ref(),
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub pool_state: UncheckedAccount<'info>,
+
+    /// CHECK: LP mint is initialized by Raydium
+    #[account(
+        mut,
+        seeds = [
+            POOL_LP_MINT_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub lp_mint: UncheckedAccount<'info>,
+
+    /// CHECK: Creator LP token account is initialized by Raydium
+    #[account(mut)]
+    pub creator_lp_token: UncheckedAccount<'info>,
+
+    /// CHECK: Token vaults are initialized by Raydium
+    #[account(
+        mut,
+        seeds = [
+            POOL_VAULT_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            token_mint.key().as_ref()
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub token_0_vault: UncheckedAccount<'info>,
+
+    /// CHECK: Token vaults are initialized by Raydium
+    #[account(
+        mut,
+        seeds = [
+            POOL_VAULT_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            buy_token_mint.key().as_ref()
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub token_1_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        address= raydium_cp_swap::create_pool_fee_reveiver::ID,
+    )]
+    pub create_pool_fee: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: Observation state is initialized by Raydium
+    #[account(
+        mut,
+        seeds = [
+            OBSERVATION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        seeds::program = cp_swap_program.key(),
+        bump,
+    )]
+    pub observation_state: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+    /// Spl token program or token program 2022，销售代币所属
+    pub token_0_program: Interface<'info, TokenInterface>,
+    /// Spl token program or token program 2022，支付代币所属
+    pub token_1_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+impl<'info> FinalizeSale<'info> {
+    pub fn process(&mut self, bump_seed: u8, sol_vault_bump: u8) -> Result<PoolSeeded> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 售罄或结束时达到软顶才能注入流动性
+        match self.sale.refresh_status(current_time)? {
+            SaleStatus::SoldOut | SaleStatus::Succeeded => {}
+            SaleStatus::LiquiditySeeded => {
+                msg!("Pool already seeded.");
+                return Err(ErrorCode::PoolAlreadySeeded.into());
+            }
+            SaleStatus::Failed | SaleStatus::Cancelled => {
+                msg!("Sale did not reach the soft cap.");
+                return Err(ErrorCode::SoftCapNotReached.into());
+            }
+            _ => {
+                msg!("Sale has not ended yet.");
+                return Err(ErrorCode::SaleNotEnded.into());
+            }
+        }
+
+        // 旧版本购买记录全部迁移后募集资金才完整，注入比例才准确
+        if self.sale.unmigrated_amount > 0 {
+            msg!("{} legacy tokens not migrated.", self.sale.unmigrated_amount);
+            return Err(ErrorCode::PurchasesNotMigrated.into());
+        }
+
+        // 池子开放时间由所有者在创建销售时设定，已经过去时立即开放，避免销售因无人及时调用而无法注入流动性
+        let open_time = std::cmp::max(self.sale.pool_open_time, current_time as u64);
+
+        // 金库中除去买家应得和未售出部分之后预留给流动性的代币
+        let reserved_amount = self.sale_sell_token_account.amount
+            .checked_sub(self.sale.sold_amount)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_sub(self.sale.remaining_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // 先按创建时锁定的比例收取协议手续费
+        let protocol_fee = (self.sale.total_raised as u128)
+            .checked_mul(self.sale.protocol_fee_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(crate::BPS_DENOMINATOR as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        let protocol_fee = u64::try_from(protocol_fee)
+            .map_err(|_| ErrorCode::Overflow)?;
+
+        let net_raised = self.sale.total_raised
+            .checked_sub(protocol_fee)
+            .ok_or(ErrorCode::Overflow)?;
+
+        if protocol_fee > 0 {
+            self.pay_protocol_fee(bump_seed, sol_vault_bump, protocol_fee)?;
+        }
+
+        // amount_1 为扣除手续费后按 liquidity_bps 注入流动性的部分，剩余归所有者
+        let amount_1 = (net_raised as u128)
+            .checked_mul(self.sale.liquidity_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(crate::BPS_DENOMINATOR as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        let amount_1 = u64::try_from(amount_1)
+            .map_err(|_| ErrorCode::Overflow)?;
+
+        // amount_0 按销售成交均价换算 amount_1 对应的代币数量，使池子开盘价与销售价格一致
+        let amount_0 = (self.sale.sold_amount as u128)
+            .checked_mul(amount_1 as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(self.sale.total_raised as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        let amount_0 = std::cmp::min(amount_0 as u64, reserved_amount);
+
+        if amount_0 == 0 {
+            msg!("No tokens reserved for liquidity.");
+            return Err(ErrorCode::InsufficientLiquidityTokens.into());
+        }
+
+        // 原生 SOL 支付时先把金库中的 lamports 包装为 wSOL
+        if self.sale.native_payment {
+            self.wrap_sol_vault(sol_vault_bump, amount_1)?;
+        }
+
+        // 池子创建者为每个销售独立的 pda，由调用者垫付租金和创建池子手续费
+        let pool_funding = self.amm_config.create_pool_fee
+            .checked_add(crate::POOL_CREATION_RENT)
+            .ok_or(ErrorCode::Overflow)?;
+
+        system_program::transfer(
+            CpiContext::new(
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.payer.to_account_info(),
+                    to: self.pda_account.to_account_info(),
+                },
+            ),
+            pool_funding,
+        )?;
+
+        let cpi_accounts = cpi::accounts::Initialize {
+            creator: self.pda_account.to_account_info(),
+            amm_config: self.amm_config.to_account_info(),
+            authority: self.authority.to_account_info(),
+            pool_state: self.pool_state.to_account_info(),
+            token_0_mint: self.token_mint.to_account_info(),
+            token_1_mint: self.buy_token_mint.to_account_info(),
+            lp_mint: self.lp_mint.to_account_info(),
+            creator_token_0: self.sale_sell_token_account.to_account_info(),
+            creator_token_1: self.sale_token_account.to_account_info(),
+            creator_lp_token: self.creator_lp_token.to_account_info(),
+            token_0_vault: self.token_0_vault.to_account_info(),
+            token_1_vault: self.token_1_vault.to_account_info(),
+            create_pool_fee: self.create_pool_fee.to_account_info(),
+            observation_state: self.observation_state.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            token_0_program: self.token_0_program.to_account_info(),
+            token_1_program: self.token_1_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            rent: self.rent.to_account_info(),
+        };
+
+        let sale_key = self.sale.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            crate::VAULT_AUTHORITY,
+            sale_key.as_ref(),
+            &[bump_seed]
+        ]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            self.cp_swap_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds
+        );
+
+        cpi::initialize(cpi_ctx, amount_0, amount_1, open_time)?;
+
+        // 退还未用完的垫付 lamports
+        let unused_funding = std::cmp::min(self.pda_account.lamports(), pool_funding);
+        if unused_funding > 0 {
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.pda_account.to_account_info(),
+                        to: self.payer.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                unused_funding,
+            )?;
+        }
+
+        self.sale.transition(SaleStatus::LiquiditySeeded)?;
+        self.sale.pool_payment_amount = amount_1;
+        self.sale.protocol_fee_amount = protocol_fee;
+        // 注入流动性即为 TGE，释放计划从此开始计算
+        self.sale.tge_time = current_time;
+        // 未注入池子的预留代币与未售出代币一起归还所有者
+        self.sale.remaining_amount = self.sale.remaining_amount
+            .checked_add(reserved_amount - amount_0)
+            .ok_or(ErrorCode::Overflow)?;
+
+        msg!("Seeded pool with {} tokens and {} buy tokens", amount_0, amount_1);
+
+        Ok(PoolSeeded {
+            sale: self.sale.key(),
+            pool_state: self.pool_state.key(),
+            token_mint: self.token_mint.key(),
+            buy_token_mint: self.buy_token_mint.key(),
+            token_amount: amount_0,
+            payment_amount: amount_1,
+            protocol_fee,
+            open_time,
+            timestamp: current_time,
+        })
+    }
+
+    /// 将协议手续费转给 treasury
+    fn pay_protocol_fee(&mut self, bump_seed: u8, sol_vault_bump: u8, fee: u64) -> Result<()> {
+        let sale_key = self.sale.key();
+
+        if self.sale.native_payment {
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::SOL_VAULT, sale_key.as_ref(), &[sol_vault_bump]]];
+
+            system_program::transfer(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.sol_vault.to_account_info(),
+                        to: self.treasury.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                fee,
+            )?;
+        } else {
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
+
+            let treasury_token_account = self.treasury_token_account
+                .as_ref()
+                .ok_or(ErrorCode::MissingTokenAccount)?;
+
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    self.token_1_program.to_account_info(),
+                    TransferChecked {
+                        from: self.sale_token_account.to_account_info(),
+                        mint: self.buy_token_mint.to_account_info(),
+                        to: treasury_token_account.to_account_info(),
+                        authority: self.pda_account.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                fee,
+                self.buy_token_mint.decimals,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// 将 SOL 金库中募集到的 lamports 转入 wSOL 金库并同步余额
+    fn wrap_sol_vault(&mut self, sol_vault_bump: u8, lamports: u64) -> Result<()> {
+        let sale_key = self.sale.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            crate::SOL_VAULT,
+            sale_key.as_ref(),
+            &[sol_vault_bump]
+        ]];
+
+        system_program::transfer(
+            CpiContext::new_with_signer(
+                self.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: self.sol_vault.to_account_info(),
+                    to: self.sale_token_account.to_account_info(),
+                },
+                signer_seeds,
+            ),
+            lamports,
+        )?;
+
+        sync_native(CpiContext::new(
+            self.token_1_program.to_account_info(),
+            SyncNative {
+                account: self.sale_token_account.to_account_info(),
+            },
+        ))?;
+
+        self.sale_token_account.reload()?;
+
+        Ok(())
+    }
+}
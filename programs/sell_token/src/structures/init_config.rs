@@ -0,0 +1,50 @@
+use super::GlobalConfig;
+use anchor_lang::prelude::*;
+
+use crate::program::SellToken;
+use super::error::ErrorCode;
+
+/// 初始化全局配置，只有程序升级权限账户可以调用
+#[derive(Accounts)]
+pub struct InitConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + core::mem::size_of::<GlobalConfig>(),
+        seeds = [crate::GLOBAL_CONFIG],
+        bump
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        constraint = program.programdata_address()? == Some(program_data.key()),
+    )]
+    pub program: Program<'info, SellToken>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(authority.key()) @ ErrorCode::Unauthorized,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitConfig<'info> {
+    pub fn process(&mut self, admin: Pubkey, treasury: Pubkey, protocol_fee_bps: u16, sale_creation_fee: u64) -> Result<()> {
+        if protocol_fee_bps as u64 > crate::MAX_PROTOCOL_FEE_BPS {
+            msg!("Protocol fee bps must not exceed {}.", crate::MAX_PROTOCOL_FEE_BPS);
+            return Err(ErrorCode::InvalidFee.into());
+        }
+
+        let config = &mut self.config;
+        config.admin = admin;
+        config.treasury = treasury;
+        config.protocol_fee_bps = protocol_fee_bps;
+        config.sale_creation_fee = sale_creation_fee;
+
+        Ok(())
+    }
+}
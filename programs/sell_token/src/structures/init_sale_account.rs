@@ -1,9 +1,40 @@
 use super::SaleAccount;
+use super::GlobalConfig;
+use super::AllowedMint;
+use super::PriceMode;
+use super::SaleStatus;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer, Mint, transfer};
+use anchor_lang::system_program;
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
 use anchor_spl::associated_token::AssociatedToken;
 use super::error::ErrorCode;
+use crate::events::SaleCreated;
 
+/// 创建销售的参数
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct InitSaleParams {
+    pub sale_amount: u64, // 销售数量（硬顶）
+    pub price_numerator: u64, // 价格分子（荷兰拍为起始价格）
+    pub price_denominator: u64, // 价格分母
+    pub price_mode: PriceMode, // 定价模式
+    pub floor_price_numerator: u64, // 荷兰拍底价分子，固定价格时忽略
+    pub price_step_count: u16, // 阶梯下降的阶梯数，仅阶梯模式使用
+    pub start_time: i64, // 开始时间
+    pub end_time: i64, // 结束时间
+    pub min_contribution: Option<u64>, // 单个钱包最低购买金额
+    pub max_contribution: Option<u64>, // 单个钱包最高购买金额
+    pub merkle_root: Option<[u8; 32]>, // 白名单 merkle root
+    pub native_payment: bool, // 是否以原生 SOL 支付
+    pub soft_cap: u64, // 软顶
+    pub pool_open_time: u64, // Raydium 池子开放交易时间，0 表示注入后立即开放
+    pub liquidity_bps: u16, // 募集资金注入流动性的比例
+    pub tge_bps: u16, // TGE 时立即解锁的比例
+    pub cliff_seconds: i64, // TGE 之后的锁定期
+    pub vesting_duration: i64, // 锁定期之后的线性释放时长
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct InitSaleAccount<'info> {
     #[account(
@@ -18,40 +49,111 @@ pub struct InitSaleAccount<'info> {
 /// CHECK:` doc comment explaining why no checks through types are necessary.
     #[account(
         mut,
-        seeds = [crate::TOKEN_SEED], 
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()], 
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    #[account(
+        seeds = [crate::GLOBAL_CONFIG],
+        bump,
+    )]
+    pub config: Box<Account<'info, GlobalConfig>>,
+
+    /// 手续费接收地址
+    #[account(
+        mut,
+        address = config.treasury,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// 支付代币必须在管理员维护的白名单中
+    #[account(
+        seeds = [crate::ALLOWED_MINT, buy_token_mint.key().as_ref()],
+        bump,
+        constraint = allowed_mint.is_enabled @ ErrorCode::PaymentMintNotAllowed,
+    )]
+    pub allowed_mint: Box<Account<'info, AllowedMint>>,
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
+    #[account(
+        mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
         bump,
     )]
-    pub pda_account: AccountInfo<'info>, //合约pda账户
+    pub sol_vault: SystemAccount<'info>,
     
     
-    pub token_mint: Account<'info, Mint>,
-    pub buy_token_mint: Account<'info, Mint>,
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = buy_token_program,
+    )]
+    pub buy_token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(
         mut,
         constraint = owner_token_account.owner == owner.key(),
-        constraint = owner_token_account.mint == token_mint.key()
+        constraint = owner_token_account.mint == token_mint.key(),
+        token::token_program = token_program,
     )]
-    pub owner_token_account: Account<'info, TokenAccount>,
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
     
     
     #[account(
         init,
         payer = owner,
         associated_token::mint = token_mint,
-        associated_token::authority = pda_account
+        associated_token::authority = pda_account,
+        associated_token::token_program = token_program,
     )]
-    pub sale_token_account: Account<'info, TokenAccount>,
+    pub sale_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// 本次销售独立的支付代币金库，原生 SOL 支付时用于注入流动性前包装 wSOL
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = buy_token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = buy_token_program,
+    )]
+    pub contract_token_account: InterfaceAccount<'info, TokenAccount>,
     
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    /// 销售代币所属的 token program（spl token 或 token 2022）
+    pub token_program: Interface<'info, TokenInterface>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> InitSaleAccount<'info> {
-    pub fn process(&mut self, sale_amount: u64, price_per_token: u64, end_time: i64) -> Result<()> {
+    pub fn process(&mut self, params: InitSaleParams) -> Result<SaleCreated> {
+        let InitSaleParams {
+            sale_amount,
+            price_numerator,
+            price_denominator,
+            price_mode,
+            floor_price_numerator,
+            price_step_count,
+            start_time,
+            end_time,
+            min_contribution,
+            max_contribution,
+            merkle_root,
+            native_payment,
+            soft_cap,
+            pool_open_time,
+            liquidity_bps,
+            tge_bps,
+            cliff_seconds,
+            vesting_duration,
+        } = params;
+
         // 验证销售数量
         if sale_amount < self.token_mint.supply / 5 {
             msg!("Sale amount is too low.");
@@ -71,46 +173,215 @@ impl<'info> InitSaleAccount<'info> {
             return Err(ErrorCode::InsufficientBalance.into());
         }
 
-        // 验证价格
-        if price_per_token == 0 {
-            msg!("Price per token cannot be zero.");
+        // 验证软顶，sale_amount 即为硬顶
+        if soft_cap == 0 || soft_cap > sale_amount {
+            msg!("Soft cap must be between 1 and sale amount.");
+            return Err(ErrorCode::InvalidSoftCap.into());
+        }
+
+        // 验证价格，价格以最小单位表示为分数，同时兼容两种代币的精度
+        if price_numerator == 0 || price_denominator == 0 {
+            msg!("Price numerator and denominator cannot be zero.");
             return Err(ErrorCode::InvalidPrice.into());
         }
 
-        // 验证结束时间
+        // 荷兰拍底价必须低于起始价格，阶梯模式至少一个阶梯
+        let (floor_price_numerator, price_step_count) = match price_mode {
+            PriceMode::Fixed => (price_numerator, 0),
+            PriceMode::LinearDecay => (floor_price_numerator, 0),
+            PriceMode::StepDecay => (floor_price_numerator, price_step_count),
+        };
+        if floor_price_numerator == 0 || floor_price_numerator > price_numerator {
+            msg!("Floor price must be between 1 and start price.");
+            return Err(ErrorCode::InvalidPrice.into());
+        }
+        if price_mode == PriceMode::StepDecay && price_step_count == 0 {
+            msg!("Step decay requires at least one step.");
+            return Err(ErrorCode::InvalidPrice.into());
+        }
+
+        // 换算为每个完整销售代币的最低成交价格后与最低价格比较
+        let whole_token = 10u128
+            .checked_pow(self.token_mint.decimals.into())
+            .ok_or(ErrorCode::Overflow)?;
+        let price_per_whole_token = (floor_price_numerator as u128)
+            .checked_mul(whole_token)
+            .ok_or(ErrorCode::Overflow)?;
+        let min_price = (self.allowed_mint.min_price as u128)
+            .checked_mul(price_denominator as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        if price_per_whole_token < min_price {
+            msg!("Price below minimum for payment mint.");
+            return Err(ErrorCode::PriceBelowMinimum.into());
+        }
+
+        // 验证开始时间
         let current_time = Clock::get()?.unix_timestamp;
-        if end_time <= current_time {
-            msg!("End time must be in the future.");
+        if start_time < current_time {
+            msg!("Start time must not be in the past.");
+            return Err(ErrorCode::InvalidStartTime.into());
+        }
+
+        // 验证结束时间
+        if end_time <= start_time {
+            msg!("End time must be after start time.");
             return Err(ErrorCode::InvalidEndTime.into());
         }
 
+        // 验证池子开放时间，必须晚于销售结束时间，保证有时间完成 finalize_sale
+        if pool_open_time != 0 && pool_open_time <= end_time as u64 {
+            msg!("Pool open time must be after end time.");
+            return Err(ErrorCode::InvalidOpenTime.into());
+        }
+
+        // 验证流动性比例
+        if liquidity_bps == 0 || liquidity_bps as u64 > crate::BPS_DENOMINATOR {
+            msg!("Liquidity bps must be between 1 and 10000.");
+            return Err(ErrorCode::InvalidLiquidityBps.into());
+        }
+
+        // 验证释放计划
+        if tge_bps as u64 > crate::BPS_DENOMINATOR || cliff_seconds < 0 || vesting_duration < 0 {
+            msg!("Invalid vesting schedule.");
+            return Err(ErrorCode::InvalidVestingSchedule.into());
+        }
+
+        // 验证单个钱包购买限额
+        let min_contribution = min_contribution.unwrap_or(0);
+        let max_contribution = max_contribution.unwrap_or(0);
+        if max_contribution > 0 && max_contribution < min_contribution {
+            msg!("Max contribution must not be lower than min contribution.");
+            return Err(ErrorCode::InvalidContributionLimits.into());
+        }
+
+        // 原生 SOL 支付时，支付代币必须为 wSOL，以便后续注入流动性
+        if native_payment && self.buy_token_mint.key() != native_mint::ID {
+            msg!("Native payment requires the wrapped SOL mint.");
+            return Err(ErrorCode::InvalidPaymentMint.into());
+        }
+
         msg!("self.token_mint.supply {}",self.token_mint.supply);
         // 划转token
-        transfer(
+        transfer_checked(
             self.into_transfer_to_vault_context(),
             
             //代币总量转入
-            self.token_mint.supply
+            self.token_mint.supply,
+            self.token_mint.decimals,
         )?;
 
+        // 带转账手续费的代币以实际到账数量为准
+        self.sale_token_account.reload()?;
+        let received_amount = self.sale_token_account.amount;
+        if received_amount < sale_amount {
+            msg!("Received {} tokens, less than sale amount.", received_amount);
+            return Err(ErrorCode::InsufficientTokens.into());
+        }
+
+        // 预留代币必须足够按销售价格注入售满时 liquidity_bps 比例的募集资金
+        // 按价格换算后代币数量与价格无关，即 sale_amount * liquidity_bps
+        let required_reserve = (sale_amount as u128)
+            .checked_mul(liquidity_bps as u128)
+            .ok_or(ErrorCode::Overflow)?
+            .checked_div(crate::BPS_DENOMINATOR as u128)
+            .ok_or(ErrorCode::Overflow)?;
+        let reserved_amount = received_amount - sale_amount;
+        if reserved_amount == 0 || (reserved_amount as u128) < required_reserve {
+            msg!("Reserved {} tokens, {} required for liquidity.", reserved_amount, required_reserve);
+            return Err(ErrorCode::InsufficientLiquidityTokens.into());
+        }
+
         let sale = &mut self.sale;
         sale.owner = self.owner.key();
         sale.token_mint = self.token_mint.key();
         sale.sale_amount = sale_amount;
         sale.remaining_amount = sale_amount;
-        sale.price_per_token = price_per_token;
+        sale.price_numerator = price_numerator;
+        sale.price_denominator = price_denominator;
+        sale.price_mode = price_mode;
+        sale.floor_price_numerator = floor_price_numerator;
+        sale.price_step_count = price_step_count;
+        sale.start_time = start_time;
         sale.end_time = end_time;
-        sale.is_active = true;
         sale.buy_token_mint = self.buy_token_mint.key();
+        sale.min_contribution = min_contribution;
+        sale.max_contribution = max_contribution;
+        sale.merkle_root = merkle_root.unwrap_or_default();
+        sale.native_payment = native_payment;
+        sale.soft_cap = soft_cap;
+        sale.sold_amount = 0;
+        sale.total_raised = 0;
+        sale.pool_open_time = pool_open_time;
+        sale.liquidity_bps = liquidity_bps;
+        sale.pool_payment_amount = 0;
+        sale.unmigrated_amount = 0;
+        sale.tge_bps = tge_bps;
+        sale.cliff_seconds = cliff_seconds;
+        sale.vesting_duration = vesting_duration;
+        sale.tge_time = 0;
+        sale.paused_at = 0;
+        sale.pending_owner = Pubkey::default();
+        sale.protocol_fee_bps = self.config.protocol_fee_bps;
+        sale.protocol_fee_amount = 0;
+        sale.deposited_amount = received_amount;
+        sale.unsold_withdrawn = 0;
+        sale.proceeds_withdrawn = 0;
+        sale.status = SaleStatus::Upcoming;
+        sale.unsettled_purchases = 0;
+
+        // 收取创建销售手续费
+        if self.config.sale_creation_fee > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.owner.to_account_info(),
+                        to: self.treasury.to_account_info(),
+                    },
+                ),
+                self.config.sale_creation_fee,
+            )?;
+        }
+
+        // 预存免租金额，保证 SOL 金库在退款过程中始终有效
+        if native_payment {
+            let rent_exempt = Rent::get()?.minimum_balance(0);
+            system_program::transfer(
+                CpiContext::new(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.owner.to_account_info(),
+                        to: self.sol_vault.to_account_info(),
+                    },
+                ),
+                rent_exempt,
+            )?;
+        }
 
-        Ok(())
+        Ok(SaleCreated {
+            sale: self.sale.key(),
+            owner: self.owner.key(),
+            token_mint: self.token_mint.key(),
+            buy_token_mint: self.buy_token_mint.key(),
+            sale_amount,
+            soft_cap,
+            price_mode,
+            price_numerator,
+            price_denominator,
+            floor_price_numerator,
+            native_payment,
+            start_time,
+            end_time,
+            timestamp: current_time,
+        })
     }
 
-    pub fn into_transfer_to_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
+    pub fn into_transfer_to_vault_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
         CpiContext::new(
             self.token_program.to_account_info(),
-            Transfer {
+            TransferChecked {
                 from: self.owner_token_account.to_account_info(),
+                mint: self.token_mint.to_account_info(),
                 to: self.sale_token_account.to_account_info(),
                 authority: self.owner.to_account_info(),
             },
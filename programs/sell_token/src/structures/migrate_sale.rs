@@ -0,0 +1,262 @@
+use super::PriceMode;
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
+use anchor_spl::associated_token::AssociatedToken;
+
+use super::error::ErrorCode;
+
+/// 旧版本的销售账户布局，只用于迁移时读取
+#[derive(AnchorDeserialize)]
+pub struct LegacySaleAccount {
+    pub owner: Pubkey,  // 所有者
+    pub token_mint: Pubkey, // 代币Mint
+    pub sale_amount: u64, // 销售数量
+    pub remaining_amount: u64, // 剩余数量
+    pub price_per_token: u64, // 每个完整代币的价格
+    pub buy_token_mint: Pubkey, // 购买代币Mint
+    pub end_time: i64, // 结束时间
+    pub is_active: bool, // 是否活跃
+}
+
+/// 旧版本所有销售共用 [TOKEN_SEED] 派生的全局 pda 作为金库权限
+/// 迁移时把旧布局的销售账户扩容并改写为新布局，销售代币从全局金库转入按销售派生的独立金库
+/// 旧金库中的支付代币是多个销售共用的，由 migrate_user_purchase 按每条购买记录的实付金额逐条转入
+/// 进行中的销售迁移后继续销售，已结束未售罄的迁移为失败，已售罄的迁移为已注入流动性
+#[derive(Accounts)]
+pub struct MigrateSale<'info> {
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub sale: AccountInfo<'info>, //旧布局的销售账户，无法按新布局反序列化
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        seeds = [crate::TOKEN_SEED],
+        bump,
+    )]
+    pub legacy_pda_account: AccountInfo<'info>, //旧版本全局pda账户
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()],
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    #[account(
+        mint::token_program = token_program,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mint::token_program = buy_token_program,
+    )]
+    pub buy_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// 旧的销售代币金库
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = legacy_pda_account,
+        token::token_program = token_program,
+    )]
+    pub legacy_sale_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 新的销售代币金库
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = token_program,
+    )]
+    pub sale_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 新的支付代币金库，接收后续逐条迁移的购买记录实付金额
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = buy_token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = buy_token_program,
+    )]
+    pub contract_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    /// 销售代币所属的 token program
+    pub token_program: Interface<'info, TokenInterface>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+}
+
+impl<'info> MigrateSale<'info> {
+    pub fn process(&mut self, legacy_bump: u8) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        let legacy = {
+            let data = self.sale.try_borrow_data()?;
+            read_legacy::<LegacySaleAccount>(&data, SaleAccount::DISCRIMINATOR)?
+        };
+
+        if legacy.owner != self.owner.key() {
+            msg!("Signer is not the sale owner.");
+            return Err(ErrorCode::Unauthorized.into());
+        }
+
+        if legacy.token_mint != self.token_mint.key() || legacy.buy_token_mint != self.buy_token_mint.key() {
+            msg!("Mint does not match the legacy sale.");
+            return Err(ErrorCode::TokenMintMismatch.into());
+        }
+
+        // 旧版本的退款、领取和提取指令以销售账户签名，与金库权限不符，从未能成功执行
+        // 因此 remaining_amount 只会因售罄变为 0，此时买家购买时已把预留代币和全部募集资金注入池子，
+        // 金库中只剩买家应得的代币；未售罄且已结束的销售按旧规则全额退款
+        let status = if legacy.remaining_amount == 0 {
+            SaleStatus::LiquiditySeeded
+        } else if !legacy.is_active || current_time > legacy.end_time {
+            SaleStatus::Failed
+        } else {
+            SaleStatus::Live
+        };
+
+        // 销售代币金库按 token_mint 区分，旧金库余额全部属于本次销售
+        let token_amount = self.legacy_sale_token_account.amount;
+        let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED, &[legacy_bump]]];
+
+        let transfer_ctx = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            TransferChecked {
+                from: self.legacy_sale_token_account.to_account_info(),
+                mint: self.token_mint.to_account_info(),
+                to: self.sale_token_account.to_account_info(),
+                authority: self.legacy_pda_account.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked(transfer_ctx, token_amount, self.token_mint.decimals)?;
+
+        // 以新金库实际到账数量作为存入数量，供提取时按记账计算
+        self.sale_token_account.reload()?;
+
+        // 旧版本按每个完整代币定价，换算为每 10^decimals 个最小单位的分数价格
+        let price_denominator = 10u64
+            .checked_pow(self.token_mint.decimals.into())
+            .ok_or(ErrorCode::Overflow)?;
+
+        let sold_amount = legacy.sale_amount
+            .checked_sub(legacy.remaining_amount)
+            .ok_or(ErrorCode::Overflow)?;
+
+        // 旧版本未售罄即退款、售罄后募集资金和剩余代币全部注入流动性且立即可领取
+        // 募集资金和未结算数量由 migrate_user_purchase 逐条累计，已注入流动性的募集资金不再计入
+        let sale = SaleAccount {
+            owner: legacy.owner,
+            token_mint: legacy.token_mint,
+            sale_amount: legacy.sale_amount,
+            remaining_amount: legacy.remaining_amount,
+            price_numerator: legacy.price_per_token,
+            price_denominator,
+            price_mode: PriceMode::Fixed,
+            floor_price_numerator: 0,
+            price_step_count: 0,
+            buy_token_mint: legacy.buy_token_mint,
+            start_time: 0,
+            end_time: legacy.end_time,
+            min_contribution: 0,
+            max_contribution: 0,
+            merkle_root: [0u8; 32],
+            native_payment: false,
+            soft_cap: legacy.sale_amount,
+            sold_amount,
+            total_raised: 0,
+            pool_open_time: 0,
+            liquidity_bps: crate::BPS_DENOMINATOR as u16,
+            pool_payment_amount: 0,
+            unmigrated_amount: sold_amount,
+            tge_bps: crate::BPS_DENOMINATOR as u16,
+            cliff_seconds: 0,
+            vesting_duration: 0,
+            tge_time: 0,
+            paused_at: 0,
+            pending_owner: Pubkey::default(),
+            protocol_fee_bps: 0,
+            protocol_fee_amount: 0,
+            deposited_amount: self.sale_token_account.amount,
+            unsold_withdrawn: 0,
+            proceeds_withdrawn: 0,
+            status,
+            unsettled_purchases: 0,
+        };
+
+        resize_account(
+            &self.sale,
+            &self.owner,
+            &self.system_program,
+            8 + core::mem::size_of::<SaleAccount>(),
+        )?;
+
+        let mut data = self.sale.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        sale.try_serialize(&mut writer)?;
+
+        msg!("Migrated {:?} sale with {} tokens, {} sold tokens pending", status, token_amount, sold_amount);
+
+        Ok(())
+    }
+}
+
+/// 校验旧布局账户的鉴别器和长度后读取，长度已是新布局说明已经迁移过
+pub(crate) fn read_legacy<T: AnchorDeserialize>(data: &[u8], discriminator: &[u8]) -> Result<T> {
+    if !data.starts_with(discriminator) {
+        return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+
+    if data.len() != discriminator.len() + core::mem::size_of::<T>() {
+        msg!("Account already migrated.");
+        return Err(ErrorCode::AlreadyMigrated.into());
+    }
+
+    let mut legacy_data = &data[discriminator.len()..];
+    T::deserialize(&mut legacy_data)
+        .map_err(|_| anchor_lang::error::ErrorCode::AccountDidNotDeserialize.into())
+}
+
+/// 扩容账户到新布局长度，由 payer 补足免租金额
+pub(crate) fn resize_account<'info>(
+    account: &AccountInfo<'info>,
+    payer: &Signer<'info>,
+    system_program: &Program<'info, System>,
+    new_len: usize,
+) -> Result<()> {
+    let required_lamports = Rent::get()?.minimum_balance(new_len);
+    let current_lamports = account.lamports();
+
+    if required_lamports > current_lamports {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                system_program::Transfer {
+                    from: payer.to_account_info(),
+                    to: account.clone(),
+                },
+            ),
+            required_lamports - current_lamports,
+        )?;
+    }
+
+    account.resize(new_len)?;
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use super::UserPurchase;
+use super::migrate_sale::{read_legacy, resize_account};
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
+
+use super::error::ErrorCode;
+
+/// 旧版本的购买记录布局，只用于迁移时读取
+#[derive(AnchorDeserialize)]
+pub struct LegacyUserPurchase {
+    pub user_address: Pubkey, // 用户地址
+    pub token_amount: u64, // 代币数量
+    pub token_price: u64, // 代币价格
+    pub token_address: Pubkey, // 代币地址
+    pub purchase_amount: u64, // 购买数量
+    pub purchase_time: i64, // 购买时间
+    pub is_claim: bool, // 是否已领取
+}
+
+/// 把旧布局的购买记录迁移为新布局，并把实付金额从旧的全局支付代币金库转入本次销售的金库
+/// 任何人都可以调用并支付扩容租金，避免个别买家不迁移而阻塞注入流动性和关闭销售
+/// 每条记录只迁移一次，迁移后按新流程退款或领取
+#[derive(Accounts)]
+pub struct MigrateUserPurchase<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        has_one = token_mint,
+        has_one = buy_token_mint,
+    )]
+    pub sale: Box<Account<'info, SaleAccount>>,
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_PURCHASE, buyer.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        owner = crate::ID,
+    )]
+    pub user_purchase: AccountInfo<'info>, //旧布局的购买记录，无法按新布局反序列化
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        seeds = [crate::TOKEN_SEED],
+        bump,
+    )]
+    pub legacy_pda_account: AccountInfo<'info>, //旧版本全局pda账户
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    #[account(
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()],
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        mint::token_program = buy_token_program,
+    )]
+    pub buy_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+/// CHECK:` doc comment explaining why no checks through types are necessary.
+    pub buyer: AccountInfo<'info>, //购买记录所属的买家，与旧记录中的用户地址校验
+
+    /// 支付扩容租金的调用者
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// 旧的支付代币金库，多个销售共用
+    #[account(
+        mut,
+        token::mint = buy_token_mint,
+        token::authority = legacy_pda_account,
+        token::token_program = buy_token_program,
+    )]
+    pub legacy_contract_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// 新的支付代币金库
+    #[account(
+        mut,
+        associated_token::mint = buy_token_mint,
+        associated_token::authority = pda_account,
+        associated_token::token_program = buy_token_program,
+    )]
+    pub contract_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> MigrateUserPurchase<'info> {
+    pub fn process(&mut self, legacy_bump: u8) -> Result<()> {
+        let legacy = {
+            let data = self.user_purchase.try_borrow_data()?;
+            read_legacy::<LegacyUserPurchase>(&data, UserPurchase::DISCRIMINATOR)?
+        };
+
+        if legacy.user_address != self.buyer.key() || legacy.token_address != self.token_mint.key() {
+            msg!("Purchase record does not belong to the buyer.");
+            return Err(ErrorCode::Unauthorized.into());
+        }
+
+        // 旧版本已领取的记录没有待结算的资金，直接改写为已结算的新记录，之后可关闭取回租金
+        if !legacy.is_claim {
+            // 未迁移数量在迁移销售时按旧版本已售数量设定，防止同一笔购买重复计入
+            self.sale.unmigrated_amount = self.sale.unmigrated_amount
+                .checked_sub(legacy.token_amount)
+                .ok_or(ErrorCode::PurchasesNotMigrated)?;
+
+            self.sale.unsettled_purchases = self.sale.unsettled_purchases
+                .checked_add(1)
+                .ok_or(ErrorCode::Overflow)?;
+        }
+
+        // 旧版本售罄时募集资金已全部注入池子，买家按新流程领取代币，不再转移支付代币
+        let purchase_amount = if legacy.is_claim {
+            0
+        } else if self.sale.status == SaleStatus::LiquiditySeeded {
+            legacy.purchase_amount
+        } else {
+            let contract_balance_before = self.contract_token_account.amount;
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED, &[legacy_bump]]];
+
+            let transfer_ctx = CpiContext::new_with_signer(
+                self.buy_token_program.to_account_info(),
+                TransferChecked {
+                    from: self.legacy_contract_token_account.to_account_info(),
+                    mint: self.buy_token_mint.to_account_info(),
+                    to: self.contract_token_account.to_account_info(),
+                    authority: self.legacy_pda_account.to_account_info(),
+                },
+                signer_seeds,
+            );
+            transfer_checked(transfer_ctx, legacy.purchase_amount, self.buy_token_mint.decimals)?;
+
+            // 以新金库实际到账数量记账，退款时按到账数量退还
+            self.contract_token_account.reload()?;
+            let received_amount = self.contract_token_account.amount
+                .checked_sub(contract_balance_before)
+                .ok_or(ErrorCode::Overflow)?;
+
+            self.sale.total_raised = self.sale.total_raised
+                .checked_add(received_amount)
+                .ok_or(ErrorCode::Overflow)?;
+
+            received_amount
+        };
+
+        let user_purchase = UserPurchase {
+            user_address: legacy.user_address,
+            token_amount: legacy.token_amount,
+            price_numerator: legacy.token_price,
+            price_denominator: self.sale.price_denominator,
+            token_address: legacy.token_address,
+            purchase_amount,
+            purchase_time: legacy.purchase_time,
+            is_claim: legacy.is_claim,
+            purchase_count: 1,
+            last_purchase_time: legacy.purchase_time,
+            claimed_amount: if legacy.is_claim { legacy.token_amount } else { 0 },
+            weighted_price_numerator: (legacy.token_amount as u128)
+                .checked_mul(legacy.token_price as u128)
+                .ok_or(ErrorCode::Overflow)?,
+        };
+
+        resize_account(
+            &self.user_purchase,
+            &self.payer,
+            &self.system_program,
+            8 + core::mem::size_of::<UserPurchase>(),
+        )?;
+
+        let mut data = self.user_purchase.try_borrow_mut_data()?;
+        let mut writer: &mut [u8] = &mut data;
+        user_purchase.try_serialize(&mut writer)?;
+
+        msg!("Migrated purchase of {} tokens for {} buy tokens", legacy.token_amount, purchase_amount);
+
+        Ok(())
+    }
+}
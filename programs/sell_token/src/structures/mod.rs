@@ -5,34 +5,595 @@ pub mod init_sale_account;
 pub mod buy_token;
 pub mod withdraw_tokens;
 pub mod withdraw_sale_tokens;
+pub mod update_merkle_root;
+pub mod finalize_sale;
+pub mod migrate_sale;
+pub mod migrate_user_purchase;
+pub mod cancel_sale;
+pub mod pause_sale;
+pub mod resume_sale;
+pub mod change_admin;
+pub mod init_config;
+pub mod update_config;
+pub mod set_allowed_mint;
+pub mod close_user_purchase;
+pub mod close_sale;
 
-// pub  mod  change_admin;
 
+//定价模式
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriceMode {
+    #[default]
+    Fixed, // 固定价格
+    LinearDecay, // 荷兰拍，价格在销售窗口内线性下降到底价
+    StepDecay, // 荷兰拍，价格在销售窗口内分阶梯下降到底价
+}
+
+//销售状态，状态转换统一由 SaleAccount::transition 校验
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub enum SaleStatus {
+    #[default]
+    Upcoming, // 尚未开始
+    Live, // 销售中
+    Paused, // 已暂停
+    SoldOut, // 已售罄，视为成功
+    Succeeded, // 结束时达到软顶
+    Failed, // 结束时未达到软顶
+    Cancelled, // 被所有者取消
+    LiquiditySeeded, // 已注入 Raydium 流动性
+    Closed, // 已关闭
+}
+
+impl SaleStatus {
+    /// 允许的状态转换
+    pub fn can_transition_to(self, to: SaleStatus) -> bool {
+        use SaleStatus::*;
+        matches!(
+            (self, to),
+            (Upcoming, Live | Paused | Cancelled)
+                | (Live, Paused | SoldOut | Succeeded | Failed | Cancelled)
+                | (Paused, Upcoming | Live | Succeeded | Failed | Cancelled)
+                | (SoldOut | Succeeded, LiquiditySeeded)
+                | (Failed | Cancelled | LiquiditySeeded, Closed)
+        )
+    }
+}
 
 #[account]
+#[derive(Default)]
 pub struct SaleAccount {
     pub owner: Pubkey,  // 所有者
     pub token_mint: Pubkey, // 代币Mint
-    pub sale_amount: u64, // 销售数量
+    pub sale_amount: u64, // 销售数量（硬顶）
     pub remaining_amount: u64, // 剩余数量
-    pub price_per_token: u64, // 每代币价格
+    pub price_numerator: u64, // 价格分子（荷兰拍为起始价格），每 price_denominator 个销售代币最小单位需支付的支付代币最小单位数量
+    pub price_denominator: u64, // 价格分母
+    pub price_mode: PriceMode, // 定价模式
+    pub floor_price_numerator: u64, // 荷兰拍底价分子，与 price_denominator 共用分母
+    pub price_step_count: u16, // 阶梯下降的阶梯数
     pub buy_token_mint: Pubkey, // 购买代币Mint
+    pub start_time: i64, // 开始时间
     pub end_time: i64, // 结束时间
-    pub is_active: bool, // 是否活跃
+    pub min_contribution: u64, // 单个钱包最低购买金额，0 表示不限制
+    pub max_contribution: u64, // 单个钱包最高购买金额，0 表示不限制
+    pub merkle_root: [u8; 32], // 白名单 merkle root，全 0 表示不限制
+    pub native_payment: bool, // 是否以原生 SOL 支付
+    pub soft_cap: u64, // 软顶，结束时售出数量达到软顶即视为成功
+    pub sold_amount: u64, // 已售出数量
+    pub total_raised: u64, // 已募集的支付代币数量
+    pub pool_open_time: u64, // Raydium 池子开放交易时间，0 表示注入后立即开放
+    pub liquidity_bps: u16, // 募集资金注入流动性的比例，剩余部分归所有者
+    pub pool_payment_amount: u64, // 实际注入流动性的支付代币数量
+    pub unmigrated_amount: u64, // 旧版本购买记录中尚未迁移的代币数量
+    pub tge_bps: u16, // TGE 时立即解锁的比例
+    pub cliff_seconds: i64, // TGE 之后的锁定期
+    pub vesting_duration: i64, // 锁定期之后的线性释放时长
+    pub tge_time: i64, // TGE 时间，注入流动性时设定
+    pub paused_at: i64, // 暂停时间
+    pub pending_owner: Pubkey, // 待接受的新所有者
+    pub protocol_fee_bps: u16, // 创建时锁定的协议手续费比例
+    pub protocol_fee_amount: u64, // 实际收取的协议手续费
+    pub deposited_amount: u64, // 金库实际收到的销售代币数量
+    pub unsold_withdrawn: u64, // 所有者已取回的销售代币数量
+    pub proceeds_withdrawn: u64, // 所有者已提取的募集资金数量
+    pub status: SaleStatus, // 销售状态
+    pub unsettled_purchases: u64, // 尚未退款或全部领取的购买记录数量
+}
+
+impl SaleAccount {
+    /// 销售窗口为 [start_time, end_time]，两端均可购买
+    pub fn has_started(&self, now: i64) -> bool {
+        now >= self.start_time
+    }
+
+    /// 超过 end_time 之后才允许领取和提取
+    pub fn has_ended(&self, now: i64) -> bool {
+        now > self.end_time
+    }
+
+    /// 售罄或结束时达到软顶即为销售成功，注入流动性前后均视为成功
+    pub fn is_successful(&self) -> bool {
+        matches!(
+            self.status,
+            SaleStatus::SoldOut | SaleStatus::Succeeded | SaleStatus::LiquiditySeeded
+        )
+    }
+
+    /// 校验并执行状态转换
+    pub fn transition(&mut self, to: SaleStatus) -> Result<()> {
+        if !self.status.can_transition_to(to) {
+            msg!("Invalid status transition from {:?} to {:?}.", self.status, to);
+            return Err(error::ErrorCode::InvalidStatusTransition.into());
+        }
+
+        self.status = to;
+
+        Ok(())
+    }
+
+    /// 按时间推进状态：开始后进入 Live，结束后按软顶判定成功或失败
+    /// 暂停只阻止购买，暂停期间可以跨过结束时间，恢复时顺延；暂停超过 MAX_PAUSE_DURATION 后
+    /// 已过结束时间的销售同样判定结果，所有者无法借暂停无限期冻结退款和领取
+    pub fn refresh_status(&mut self, now: i64) -> Result<SaleStatus> {
+        if self.status == SaleStatus::Upcoming && self.has_started(now) {
+            self.transition(SaleStatus::Live)?;
+        }
+
+        let should_settle = match self.status {
+            SaleStatus::Live => self.has_ended(now),
+            SaleStatus::Paused => {
+                self.has_ended(now) && now > self.paused_at.saturating_add(crate::MAX_PAUSE_DURATION)
+            }
+            _ => false,
+        };
+
+        if should_settle {
+            if self.sold_amount >= self.soft_cap {
+                self.transition(SaleStatus::Succeeded)?;
+            } else {
+                self.transition(SaleStatus::Failed)?;
+            }
+        }
+
+        Ok(self.status)
+    }
+
+    pub fn is_whitelisted(&self) -> bool {
+        self.merkle_root != [0u8; 32]
+    }
+
+    /// 从暂停中恢复，暂停时长顺延到开始、结束和池子开放时间
+    /// 荷兰拍价格随时间下降，总是整体顺延使价格在暂停期间冻结；固定价格由所有者选择是否顺延结束时间
+    /// 未顺延且已过结束时间时恢复后立即判定结果
+    pub fn resume(&mut self, now: i64, extend_end_time: bool) -> Result<()> {
+        let paused_duration = now
+            .checked_sub(self.paused_at)
+            .ok_or(error::ErrorCode::Overflow)?;
+        let is_dutch = self.price_mode != PriceMode::Fixed;
+
+        if is_dutch {
+            self.start_time = self.start_time
+                .checked_add(paused_duration)
+                .ok_or(error::ErrorCode::Overflow)?;
+        }
+
+        if extend_end_time || is_dutch {
+            self.end_time = self.end_time
+                .checked_add(paused_duration)
+                .ok_or(error::ErrorCode::Overflow)?;
+
+            if self.pool_open_time != 0 {
+                self.pool_open_time = self.pool_open_time
+                    .checked_add(paused_duration as u64)
+                    .ok_or(error::ErrorCode::Overflow)?;
+            }
+
+            msg!("End time extended by {} seconds", paused_duration);
+        }
+
+        // 恢复到暂停前的阶段
+        if self.has_started(now) {
+            self.transition(SaleStatus::Live)?;
+        } else {
+            self.transition(SaleStatus::Upcoming)?;
+        }
+        self.paused_at = 0;
+        self.refresh_status(now)?;
+
+        Ok(())
+    }
+
+    /// 当前价格分子，荷兰拍按 [start_time, end_time] 内已经过的时间从起始价格下降到底价
+    pub fn current_price_numerator(&self, now: i64) -> Result<u64> {
+        if self.price_mode == PriceMode::Fixed || now <= self.start_time {
+            return Ok(self.price_numerator);
+        }
+
+        let duration = (self.end_time - self.start_time) as u128;
+        let elapsed = std::cmp::min((now - self.start_time) as u128, duration);
+        let price_drop = (self.price_numerator - self.floor_price_numerator) as u128;
+
+        // 阶梯模式下按已完成的阶梯数计算降幅
+        let (progress, total) = match self.price_mode {
+            PriceMode::StepDecay => {
+                let steps = self.price_step_count as u128;
+                let steps_passed = elapsed
+                    .checked_mul(steps)
+                    .ok_or(error::ErrorCode::Overflow)?
+                    .checked_div(duration)
+                    .ok_or(error::ErrorCode::Overflow)?;
+                (steps_passed, steps)
+            }
+            _ => (elapsed, duration),
+        };
+
+        let decayed = price_drop
+            .checked_mul(progress)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_div(total)
+            .ok_or(error::ErrorCode::Overflow)? as u64;
+
+        Ok(self.price_numerator - decayed)
+    }
+
+    /// 按 now 时的价格，payment 个支付代币最小单位可购买的销售代币最小单位数量，向下取整
+    pub fn tokens_for_payment(&self, payment: u64, now: i64) -> Result<u64> {
+        let tokens = (payment as u128)
+            .checked_mul(self.price_denominator as u128)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_div(self.current_price_numerator(now)? as u128)
+            .ok_or(error::ErrorCode::Overflow)?;
+
+        u64::try_from(tokens).map_err(|_| error::ErrorCode::Overflow.into())
+    }
+
+    /// 按 now 时的价格，购买 tokens 个销售代币最小单位需支付的数量，向上取整，避免按最小单位购买时少付
+    pub fn payment_for_tokens(&self, tokens: u64, now: i64) -> Result<u64> {
+        let payment = (tokens as u128)
+            .checked_mul(self.current_price_numerator(now)? as u128)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_add(self.price_denominator as u128 - 1)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_div(self.price_denominator as u128)
+            .ok_or(error::ErrorCode::Overflow)?;
+
+        u64::try_from(payment).map_err(|_| error::ErrorCode::Overflow.into())
+    }
+
+    /// 所有者应得的募集资金，扣除注入流动性和协议手续费的部分
+    pub fn owner_proceeds(&self) -> Result<u64> {
+        let proceeds = self.total_raised
+            .checked_sub(self.pool_payment_amount)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_sub(self.protocol_fee_amount)
+            .ok_or(error::ErrorCode::Overflow)?;
+
+        Ok(proceeds)
+    }
+
+    /// 按 TGE 比例、锁定期和线性释放计算 total 中已解锁的数量
+    pub fn vested_amount(&self, total: u64, now: i64) -> Result<u64> {
+        if self.status != SaleStatus::LiquiditySeeded || now < self.tge_time {
+            return Ok(0);
+        }
+
+        let tge_amount = (total as u128)
+            .checked_mul(self.tge_bps as u128)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_div(crate::BPS_DENOMINATOR as u128)
+            .ok_or(error::ErrorCode::Overflow)? as u64;
+
+        let vesting_start = self.tge_time
+            .checked_add(self.cliff_seconds)
+            .ok_or(error::ErrorCode::Overflow)?;
+
+        if now < vesting_start {
+            return Ok(tge_amount);
+        }
+
+        let elapsed = now - vesting_start;
+        if self.vesting_duration == 0 || elapsed >= self.vesting_duration {
+            return Ok(total);
+        }
+
+        let linear_amount = ((total - tge_amount) as u128)
+            .checked_mul(elapsed as u128)
+            .ok_or(error::ErrorCode::Overflow)?
+            .checked_div(self.vesting_duration as u128)
+            .ok_or(error::ErrorCode::Overflow)? as u64;
+
+        Ok(tge_amount + linear_amount)
+    }
+}
+
+//全局配置
+#[account]
+pub struct GlobalConfig {
+    pub admin: Pubkey, // 管理员
+    pub treasury: Pubkey, // 手续费接收地址
+    pub protocol_fee_bps: u16, // 募集资金的协议手续费比例
+    pub sale_creation_fee: u64, // 创建销售的手续费（lamports）
+}
+
+//允许的支付代币
+#[account]
+pub struct AllowedMint {
+    pub mint: Pubkey, // 支付代币Mint
+    pub min_price: u64, // 每个完整销售代币的最低价格，以支付代币最小单位计
+    pub is_enabled: bool, // 是否启用
 }
 
 //用户购买结构 
 #[account]
 pub struct UserPurchase {
     pub user_address: Pubkey, // 用户地址
-    pub token_amount: u64, // 代币数量
-    pub token_price: u64, // 代币价格
+    pub token_amount: u64, // 累计购买的代币数量
+    pub price_numerator: u64, // 按成交数量加权的平均成交价格分子，荷兰拍每次追加购买的成交价格不同
+    pub price_denominator: u64, // 价格分母
     pub token_address: Pubkey, // 代币地址
-    pub purchase_amount: u64, // 购买数量
-    pub purchase_time: i64, // 购买时间
+    pub purchase_amount: u64, // 累计实际支付金额
+    pub purchase_time: i64, // 首次购买时间
     pub is_claim: bool, // 是否已领取
+    pub purchase_count: u64, // 购买次数
+    pub last_purchase_time: i64, // 最后购买时间
+    pub claimed_amount: u64, // 已领取的代币数量
+    pub weighted_price_numerator: u128, // 每次成交数量与成交价格分子乘积的累计值
+}
+
+impl UserPurchase {
+    /// 记录一次成交的价格，token_amount 需已累加本次成交数量
+    /// 按成交数量加权累计，追加购买不会覆盖之前的成交价格
+    pub fn record_price(&mut self, filled_amount: u64, price_numerator: u64, price_denominator: u64) -> Result<()> {
+        self.weighted_price_numerator = (filled_amount as u128)
+            .checked_mul(price_numerator as u128)
+            .and_then(|weighted| weighted.checked_add(self.weighted_price_numerator))
+            .ok_or(error::ErrorCode::Overflow)?;
+
+        self.price_numerator = u64::try_from(self.weighted_price_numerator / self.token_amount.max(1) as u128)
+            .map_err(|_| error::ErrorCode::Overflow)?;
+        self.price_denominator = price_denominator;
+
+        Ok(())
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dutch_sale(price_mode: PriceMode) -> SaleAccount {
+        SaleAccount {
+            start_time: 100,
+            end_time: 200,
+            price_numerator: 1_000,
+            price_denominator: 1,
+            floor_price_numerator: 500,
+            price_step_count: 4,
+            price_mode,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fixed_price_does_not_decay() {
+        let sale = SaleAccount {
+            floor_price_numerator: 1_000,
+            ..dutch_sale(PriceMode::Fixed)
+        };
+        for now in [0, 100, 150, 200, 300] {
+            assert_eq!(sale.current_price_numerator(now).unwrap(), 1_000);
+        }
+    }
+
+    #[test]
+    fn linear_decay_reaches_floor_at_end_time() {
+        let sale = dutch_sale(PriceMode::LinearDecay);
+        assert_eq!(sale.current_price_numerator(50).unwrap(), 1_000);
+        assert_eq!(sale.current_price_numerator(100).unwrap(), 1_000);
+        assert_eq!(sale.current_price_numerator(150).unwrap(), 750);
+        assert_eq!(sale.current_price_numerator(199).unwrap(), 505);
+        assert_eq!(sale.current_price_numerator(200).unwrap(), 500);
+        assert_eq!(sale.current_price_numerator(500).unwrap(), 500);
+    }
+
+    #[test]
+    fn step_decay_drops_once_per_step() {
+        let sale = dutch_sale(PriceMode::StepDecay);
+        assert_eq!(sale.current_price_numerator(124).unwrap(), 1_000);
+        assert_eq!(sale.current_price_numerator(125).unwrap(), 875);
+        assert_eq!(sale.current_price_numerator(150).unwrap(), 750);
+        assert_eq!(sale.current_price_numerator(199).unwrap(), 625);
+        assert_eq!(sale.current_price_numerator(200).unwrap(), 500);
+    }
 
+    #[test]
+    fn payment_uses_current_price() {
+        let sale = dutch_sale(PriceMode::LinearDecay);
+        assert_eq!(sale.payment_for_tokens(2, 100).unwrap(), 2_000);
+        assert_eq!(sale.payment_for_tokens(2, 150).unwrap(), 1_500);
+        assert_eq!(sale.tokens_for_payment(1_500, 150).unwrap(), 2);
+        assert_eq!(sale.tokens_for_payment(1_499, 150).unwrap(), 1);
+    }
 
+    #[test]
+    fn fractional_price_rounds_in_favour_of_the_sale() {
+        // 每 2 个最小单位 3 个支付代币最小单位
+        let sale = SaleAccount {
+            price_numerator: 3,
+            price_denominator: 2,
+            ..Default::default()
+        };
+        assert_eq!(sale.tokens_for_payment(10, 0).unwrap(), 6);
+        assert_eq!(sale.payment_for_tokens(6, 0).unwrap(), 9);
+        assert_eq!(sale.payment_for_tokens(5, 0).unwrap(), 8);
+        assert_eq!(sale.payment_for_tokens(1, 0).unwrap(), 2);
 
+        // 按计算出的数量收费不会超过买家给出的金额，也不会少收
+        for payment in 1..200u64 {
+            let tokens = sale.tokens_for_payment(payment, 0).unwrap();
+            let cost = sale.payment_for_tokens(tokens, 0).unwrap();
+            assert!(cost <= payment);
+            assert!(cost * 2 >= tokens * 3);
+        }
+    }
+
+    #[test]
+    fn price_math_reports_overflow() {
+        let sale = SaleAccount {
+            price_numerator: 1,
+            price_denominator: u64::MAX,
+            ..Default::default()
+        };
+        assert!(sale.tokens_for_payment(2, 0).is_err());
+    }
+
+    #[test]
+    fn top_ups_record_the_weighted_price_paid() {
+        let mut purchase = UserPurchase {
+            user_address: Pubkey::default(),
+            token_amount: 0,
+            price_numerator: 0,
+            price_denominator: 0,
+            token_address: Pubkey::default(),
+            purchase_amount: 0,
+            purchase_time: 0,
+            is_claim: false,
+            purchase_count: 0,
+            last_purchase_time: 0,
+            claimed_amount: 0,
+            weighted_price_numerator: 0,
+        };
+
+        purchase.token_amount = 100;
+        purchase.record_price(100, 1_000, 1).unwrap();
+        assert_eq!(purchase.price_numerator, 1_000);
+
+        purchase.token_amount = 400;
+        purchase.record_price(300, 600, 1).unwrap();
+        assert_eq!(purchase.weighted_price_numerator, 280_000);
+        assert_eq!(purchase.price_numerator, 700);
+        assert_eq!(purchase.price_denominator, 1);
+    }
+
+    fn vesting_sale() -> SaleAccount {
+        SaleAccount {
+            status: SaleStatus::LiquiditySeeded,
+            tge_time: 1_000,
+            tge_bps: 2_000,
+            cliff_seconds: 10,
+            vesting_duration: 100,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn nothing_vests_before_liquidity_is_seeded() {
+        let sale = SaleAccount {
+            status: SaleStatus::SoldOut,
+            ..vesting_sale()
+        };
+        assert_eq!(sale.vested_amount(1_000, 5_000).unwrap(), 0);
+        assert_eq!(vesting_sale().vested_amount(1_000, 999).unwrap(), 0);
+    }
+
+    #[test]
+    fn vesting_releases_tge_share_then_linearly_after_cliff() {
+        let sale = vesting_sale();
+        assert_eq!(sale.vested_amount(1_000, 1_000).unwrap(), 200);
+        assert_eq!(sale.vested_amount(1_000, 1_009).unwrap(), 200);
+        assert_eq!(sale.vested_amount(1_000, 1_010).unwrap(), 200);
+        assert_eq!(sale.vested_amount(1_000, 1_060).unwrap(), 600);
+        assert_eq!(sale.vested_amount(1_000, 1_109).unwrap(), 992);
+        assert_eq!(sale.vested_amount(1_000, 1_110).unwrap(), 1_000);
+        assert_eq!(sale.vested_amount(1_000, 9_999).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn vesting_without_duration_unlocks_everything_after_cliff() {
+        let sale = SaleAccount {
+            vesting_duration: 0,
+            ..vesting_sale()
+        };
+        assert_eq!(sale.vested_amount(1_000, 1_005).unwrap(), 200);
+        assert_eq!(sale.vested_amount(1_000, 1_010).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn status_transitions_follow_the_lifecycle() {
+        use SaleStatus::*;
+
+        assert!(Upcoming.can_transition_to(Live));
+        assert!(Live.can_transition_to(SoldOut));
+        assert!(Paused.can_transition_to(Failed));
+        assert!(Succeeded.can_transition_to(LiquiditySeeded));
+        assert!(LiquiditySeeded.can_transition_to(Closed));
+        assert!(Cancelled.can_transition_to(Closed));
+
+        assert!(!Upcoming.can_transition_to(Succeeded));
+        assert!(!Live.can_transition_to(Upcoming));
+        assert!(!SoldOut.can_transition_to(Closed));
+        assert!(!Failed.can_transition_to(LiquiditySeeded));
+        assert!(!LiquiditySeeded.can_transition_to(Cancelled));
+        for to in [Upcoming, Live, Paused, SoldOut, Succeeded, Failed, Cancelled, LiquiditySeeded, Closed] {
+            assert!(!Closed.can_transition_to(to));
+        }
+    }
+
+    fn paused_sale(price_mode: PriceMode) -> SaleAccount {
+        SaleAccount {
+            status: SaleStatus::Paused,
+            paused_at: 150,
+            soft_cap: 10,
+            sold_amount: 5,
+            ..dutch_sale(price_mode)
+        }
+    }
+
+    #[test]
+    fn pause_may_cross_end_time_until_the_maximum_pause() {
+        let deadline = 150 + crate::MAX_PAUSE_DURATION;
+        let mut sale = paused_sale(PriceMode::Fixed);
+        assert_eq!(sale.refresh_status(201).unwrap(), SaleStatus::Paused);
+        assert_eq!(sale.refresh_status(deadline).unwrap(), SaleStatus::Paused);
+        assert_eq!(sale.refresh_status(deadline + 1).unwrap(), SaleStatus::Failed);
+    }
+
+    #[test]
+    fn resume_with_extension_restores_the_remaining_window() {
+        let mut sale = paused_sale(PriceMode::Fixed);
+        sale.resume(250, true).unwrap();
+        assert_eq!(sale.status, SaleStatus::Live);
+        assert_eq!(sale.end_time, 300);
+        assert_eq!(sale.paused_at, 0);
+    }
+
+    #[test]
+    fn resume_without_extension_after_end_time_settles() {
+        let mut sale = paused_sale(PriceMode::Fixed);
+        sale.resume(250, false).unwrap();
+        assert_eq!(sale.status, SaleStatus::Failed);
+    }
+
+    #[test]
+    fn dutch_price_is_frozen_while_paused() {
+        let mut sale = paused_sale(PriceMode::LinearDecay);
+        let price_at_pause = sale.current_price_numerator(150).unwrap();
+        sale.resume(180, false).unwrap();
+        assert_eq!(sale.status, SaleStatus::Live);
+        assert_eq!((sale.start_time, sale.end_time), (130, 230));
+        assert_eq!(sale.current_price_numerator(180).unwrap(), price_at_pause);
+    }
+
+    #[test]
+    fn refresh_status_settles_live_sales_after_end_time() {
+        let mut sale = SaleAccount {
+            status: SaleStatus::Upcoming,
+            start_time: 100,
+            end_time: 200,
+            soft_cap: 10,
+            sold_amount: 10,
+            ..Default::default()
+        };
+        assert_eq!(sale.refresh_status(300).unwrap(), SaleStatus::Succeeded);
+    }
+}
@@ -0,0 +1,40 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+
+/// 所有者紧急暂停销售，暂停期间禁止购买
+#[derive(Accounts)]
+pub struct PauseSale<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        constraint = sale.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+impl<'info> PauseSale<'info> {
+    pub fn process(&mut self) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 只有 Upcoming 或 Live 状态可以暂停，结束后状态会先推进为成功或失败
+        self.sale.refresh_status(current_time)?;
+        self.sale.transition(SaleStatus::Paused)?;
+        self.sale.paused_at = current_time;
+
+        msg!("Sale paused at {}", current_time);
+
+        Ok(())
+    }
+}
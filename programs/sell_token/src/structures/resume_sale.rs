@@ -0,0 +1,43 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+
+/// 所有者恢复销售，可选择按暂停时长顺延结束时间，荷兰拍总是整体顺延
+#[derive(Accounts)]
+pub struct ResumeSale<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        constraint = sale.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+impl<'info> ResumeSale<'info> {
+    pub fn process(&mut self, extend_end_time: bool) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 暂停超过最长时长且已过结束时间后已被判定为成功或失败，不能再恢复
+        if self.sale.refresh_status(current_time)? != SaleStatus::Paused {
+            msg!("Sale not paused.");
+            return Err(ErrorCode::SaleNotPaused.into());
+        }
+
+        self.sale.resume(current_time, extend_end_time)?;
+
+        msg!("Sale resumed at {}", current_time);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use super::GlobalConfig;
+use super::AllowedMint;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+
+/// 管理员添加、更新或停用允许的支付代币
+#[derive(Accounts)]
+pub struct SetAllowedMint<'info> {
+    #[account(
+        seeds = [crate::GLOBAL_CONFIG],
+        bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + core::mem::size_of::<AllowedMint>(),
+        seeds = [crate::ALLOWED_MINT, mint.key().as_ref()],
+        bump
+    )]
+    pub allowed_mint: Account<'info, AllowedMint>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SetAllowedMint<'info> {
+    pub fn process(&mut self, min_price: u64, is_enabled: bool) -> Result<()> {
+        let allowed_mint = &mut self.allowed_mint;
+        allowed_mint.mint = self.mint.key();
+        allowed_mint.min_price = min_price;
+        allowed_mint.is_enabled = is_enabled;
+
+        msg!("Payment mint {} enabled: {}, min price: {}", allowed_mint.mint, is_enabled, min_price);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use super::GlobalConfig;
+use anchor_lang::prelude::*;
+
+use super::error::ErrorCode;
+
+/// 管理员更新全局配置
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [crate::GLOBAL_CONFIG],
+        bump,
+        constraint = config.admin == admin.key() @ ErrorCode::Unauthorized,
+    )]
+    pub config: Account<'info, GlobalConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+impl<'info> UpdateConfig<'info> {
+    /// 传入 None 的字段保持不变
+    pub fn process(
+        &mut self,
+        admin: Option<Pubkey>,
+        treasury: Option<Pubkey>,
+        protocol_fee_bps: Option<u16>,
+        sale_creation_fee: Option<u64>,
+    ) -> Result<()> {
+        let config = &mut self.config;
+
+        if let Some(protocol_fee_bps) = protocol_fee_bps {
+            if protocol_fee_bps as u64 > crate::MAX_PROTOCOL_FEE_BPS {
+                msg!("Protocol fee bps must not exceed {}.", crate::MAX_PROTOCOL_FEE_BPS);
+                return Err(ErrorCode::InvalidFee.into());
+            }
+            config.protocol_fee_bps = protocol_fee_bps;
+        }
+
+        if let Some(admin) = admin {
+            config.admin = admin;
+        }
+
+        if let Some(treasury) = treasury {
+            config.treasury = treasury;
+        }
+
+        if let Some(sale_creation_fee) = sale_creation_fee {
+            config.sale_creation_fee = sale_creation_fee;
+        }
+
+        Ok(())
+    }
+}
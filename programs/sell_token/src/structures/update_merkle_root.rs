@@ -0,0 +1,41 @@
+use super::SaleAccount;
+use super::SaleStatus;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+use super::error::ErrorCode;
+
+/// 销售开始前，所有者更新白名单 merkle root
+#[derive(Accounts)]
+pub struct UpdateMerkleRoot<'info> {
+    #[account(
+        mut,
+        seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
+        bump,
+        constraint = sale.owner == owner.key() @ ErrorCode::Unauthorized,
+    )]
+    pub sale: Account<'info, SaleAccount>,
+
+    #[account(
+        constraint = token_mint.key() == sale.token_mint,
+    )]
+    pub token_mint: InterfaceAccount<'info, Mint>,
+
+    pub owner: Signer<'info>,
+}
+
+impl<'info> UpdateMerkleRoot<'info> {
+    pub fn process(&mut self, merkle_root: Option<[u8; 32]>) -> Result<()> {
+        let current_time = Clock::get()?.unix_timestamp;
+
+        // 销售开始后不允许修改白名单
+        if self.sale.refresh_status(current_time)? != SaleStatus::Upcoming {
+            msg!("Sale has already started.");
+            return Err(ErrorCode::SaleAlreadyStarted.into());
+        }
+
+        self.sale.merkle_root = merkle_root.unwrap_or_default();
+
+        Ok(())
+    }
+}
@@ -1,32 +1,49 @@
 use super::SaleAccount;
+use super::SaleStatus;
 use super::UserPurchase;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Transfer, Mint, transfer};
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
 use anchor_spl::associated_token::AssociatedToken;
 
 use super::error::ErrorCode;
+use crate::events::ProceedsWithdrawn;
 
 /// 销售账户所有者提取代币的结构体
 /// 用于处理销售结束后，所有者提取剩余代币或销售所得的购买代币
+/// 未达到软顶或已取消时只能取回存入的代币，募集资金留作买家退款
+/// 成功时募集资金中未注入流动性的部分归所有者
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WithdrawSaleTokens<'info> {
     /// 销售账户
     /// 验证：
-    /// 1. 销售必须处于活跃状态
-    /// 2. 调用者必须是销售账户的所有者
+    /// 1. 调用者必须是销售账户的所有者
+    /// 2. 销售代币和购买代币必须与销售账户记录一致
     #[account(
         mut,
         seeds = [crate::TOKEN_SEED, token_mint.key().as_ref()],
-        bump
+        bump,
+        has_one = owner @ ErrorCode::Unauthorized,
+        has_one = token_mint,
+        has_one = buy_token_mint,
     )]
     pub sale: Account<'info, SaleAccount>,
 /// CHECK:` doc comment explaining why no checks through types are necessary.
     #[account(
         mut,
-        seeds = [crate::TOKEN_SEED], 
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()], 
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
+    #[account(
+        mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
         bump,
     )]
-    pub pda_account: AccountInfo<'info>, //合约pda账户
+    pub sol_vault: SystemAccount<'info>,
     
     
     /// 销售代币的Mint账户
@@ -34,16 +51,18 @@ pub struct WithdrawSaleTokens<'info> {
     #[account(
         mut,
         constraint = token_mint.key() == sale.token_mint,
+        mint::token_program = token_program,
     )]
-    pub token_mint: Account<'info, Mint>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
     
     /// 购买代币的Mint账户
     /// 验证：必须与销售账户中记录的购买代币Mint一致
     #[account(
         mut,
         constraint = buy_token_mint.key() == sale.buy_token_mint,
+        mint::token_program = buy_token_program,
     )]
-    pub buy_token_mint: Account<'info, Mint>,
+    pub buy_token_mint: InterfaceAccount<'info, Mint>,
 
     /// 销售账户所有者
     /// 必须是交易的签名者
@@ -57,9 +76,10 @@ pub struct WithdrawSaleTokens<'info> {
     #[account(
         mut,
         constraint = owner_token_account.owner == owner.key(),
-        constraint = owner_token_account.mint == token_mint.key()
+        constraint = owner_token_account.mint == token_mint.key(),
+        token::token_program = token_program,
     )]
-    pub owner_token_account: Account<'info, TokenAccount>,
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
     
     /// 销售账户的代币账户
     /// 验证：
@@ -68,98 +88,159 @@ pub struct WithdrawSaleTokens<'info> {
     #[account(
         mut,
         constraint = sale_token_account.owner == pda_account.key(),
-        constraint = sale_token_account.mint == token_mint.key()
+        constraint = sale_token_account.mint == token_mint.key(),
+        token::token_program = token_program,
     )]
-    pub sale_token_account: Account<'info, TokenAccount>,
+    pub sale_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    /// 所有者的购买代币账户
+    /// 所有者的购买代币账户，原生 SOL 支付时不需要
     /// 验证：
     /// 1. 账户所有者必须是销售账户所有者
     /// 2. 代币类型必须与购买代币一致
     #[account(
         mut,
         constraint = owner_buy_token_account.owner == owner.key(),
-        constraint = owner_buy_token_account.mint == buy_token_mint.key()
+        constraint = owner_buy_token_account.mint == buy_token_mint.key(),
+        token::token_program = buy_token_program,
     )]
-    pub owner_buy_token_account: Account<'info, TokenAccount>,
+    pub owner_buy_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
-    /// 合约的购买代币账户
+    /// 合约的购买代币账户，原生 SOL 支付时不需要
     /// 验证：
     /// 1. 账户所有者必须是销售账户
     /// 2. 代币类型必须与购买代币一致
     #[account(
         mut,
         constraint = contract_token_account.owner == pda_account.key(),
-        constraint = contract_token_account.mint == buy_token_mint.key()
+        constraint = contract_token_account.mint == buy_token_mint.key(),
+        token::token_program = buy_token_program,
     )]
-    pub contract_token_account: Account<'info, TokenAccount>,
+    pub contract_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     /// 系统程序
     pub system_program: Program<'info, System>,
-    /// 代币程序
-    pub token_program: Program<'info, Token>,
+    /// 销售代币所属的 token program
+    pub token_program: Interface<'info, TokenInterface>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
     /// 关联代币程序
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> WithdrawSaleTokens<'info> {
     /// 处理代币提取的主要逻辑
-    pub fn process(&mut self,bump_seed:u8) -> Result<()> {
+    pub fn process(&mut self,bump_seed:u8, sol_vault_bump: u8) -> Result<ProceedsWithdrawn> {
         // 获取当前时间
         let current_time = Clock::get()?.unix_timestamp;
         
-        // 检查销售是否已结束
-        if current_time < self.sale.end_time {
+        // 销售结束或取消后判定结果，达到软顶为成功，否则募集资金留作买家退款
+        let status = self.sale.refresh_status(current_time)?;
+        if !matches!(
+            status,
+            SaleStatus::Failed | SaleStatus::Cancelled | SaleStatus::SoldOut | SaleStatus::Succeeded | SaleStatus::LiquiditySeeded
+        ) {
             msg!("Sale has not ended yet.");
             return Err(ErrorCode::SaleNotEnded.into());
         }
+        let is_successful = self.sale.is_successful();
 
-        // 获取销售账户中的代币余额
-        let sale_balance = self.sale_token_account.amount;
-        let contract_balance = self.contract_token_account.amount;
+        // 按记账计算可提取数量，不依赖金库余额，已提取的部分不能重复提取
+        // 成功时只退还未售出的代币，失败时退还全部存入的代币
+        let sale_balance = if is_successful {
+            self.sale.remaining_amount
+        } else {
+            self.sale.deposited_amount
+                .checked_sub(self.sale.unsold_withdrawn)
+                .ok_or(ErrorCode::Overflow)?
+        };
+
+        // 成功时募集资金中扣除流动性和手续费的部分归所有者
+        let contract_balance = if is_successful {
+            self.sale.owner_proceeds()?
+                .checked_sub(self.sale.proceeds_withdrawn)
+                .ok_or(ErrorCode::Overflow)?
+        } else {
+            0
+        };
         
         // 处理代币提取逻辑
         if sale_balance > 0 {
             // 如果还有剩余代币（未卖完）
-            // 验证剩余代币数量是否与销售记录一致
-            if sale_balance != self.sale.remaining_amount {
-                msg!("Token balance mismatch.");
-                return Err(ErrorCode::BalanceMismatch.into());
-            }
-
             // 构建签名者种子
-            let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED, &[bump_seed]]];
+            let sale_key = self.sale.key();
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
 
             // 转移剩余代币回所有者账户
             let transfer_ctx = CpiContext::new_with_signer(
                 self.token_program.to_account_info(),
-                Transfer {
+                TransferChecked {
                     from: self.sale_token_account.to_account_info(),
+                    mint: self.token_mint.to_account_info(),
                     to: self.owner_token_account.to_account_info(),
-                    authority: self.sale.to_account_info(),
+                    authority: self.pda_account.to_account_info(),
                 },
                 signer_seeds,
             );
-            transfer(transfer_ctx, sale_balance)?;
+            transfer_checked(transfer_ctx, sale_balance, self.token_mint.decimals)?;
 
+            self.sale.unsold_withdrawn = self.sale.unsold_withdrawn
+                .checked_add(sale_balance)
+                .ok_or(ErrorCode::Overflow)?;
+            if is_successful {
+                self.sale.remaining_amount = 0;
+            }
 
             msg!("Withdrew {} unsold tokens back to owner", sale_balance);
         } else if contract_balance > 0 {
-            // 如果代币已全部售出，转移购买代币到所有者账户
-            // 构建签名者种子
-            let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED, &[bump_seed]]];
+            // 销售成功且未售出代币已提取，转移购买代币到所有者账户
+            if status != SaleStatus::LiquiditySeeded {
+                msg!("Pool not seeded.");
+                return Err(ErrorCode::PoolNotSeeded.into());
+            }
 
-            // 转移购买代币到所有者账户
-            let transfer_ctx = CpiContext::new_with_signer(
-                self.token_program.to_account_info(),
-                Transfer {
-                    from: self.contract_token_account.to_account_info(),
-                    to: self.owner_buy_token_account.to_account_info(),
-                    authority: self.sale.to_account_info(),
-                },
-                signer_seeds
-            );
-            transfer(transfer_ctx, contract_balance)?;
+            if self.sale.native_payment {
+                // 原生 SOL 支付，直接从 SOL 金库转出 lamports
+                let sale_key = self.sale.key();
+                let signer_seeds: &[&[&[u8]]] = &[&[crate::SOL_VAULT, sale_key.as_ref(), &[sol_vault_bump]]];
+
+                let transfer_ctx = CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: self.sol_vault.to_account_info(),
+                        to: self.owner.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                system_program::transfer(transfer_ctx, contract_balance)?;
+            } else {
+                // 构建签名者种子
+                let sale_key = self.sale.key();
+                let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
+
+                let contract_token_account = self.contract_token_account
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingTokenAccount)?;
+                let owner_buy_token_account = self.owner_buy_token_account
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingTokenAccount)?;
+
+                // 转移购买代币到所有者账户
+                let transfer_ctx = CpiContext::new_with_signer(
+                    self.buy_token_program.to_account_info(),
+                    TransferChecked {
+                        from: contract_token_account.to_account_info(),
+                        mint: self.buy_token_mint.to_account_info(),
+                        to: owner_buy_token_account.to_account_info(),
+                        authority: self.pda_account.to_account_info(),
+                    },
+                    signer_seeds
+                );
+                transfer_checked(transfer_ctx, contract_balance, self.buy_token_mint.decimals)?;
+            }
+
+            self.sale.proceeds_withdrawn = self.sale.proceeds_withdrawn
+                .checked_add(contract_balance)
+                .ok_or(ErrorCode::Overflow)?;
 
 
             msg!("Withdrew {} buy tokens to owner", contract_balance);
@@ -169,10 +250,15 @@ impl<'info> WithdrawSaleTokens<'info> {
             return Err(ErrorCode::NoTokensToWithdraw.into());
         }
 
-        // 更新销售账户状态
-        self.sale.remaining_amount = 0;
-        self.sale.is_active = false;
-
-        Ok(())
+        // 每次只提取一种，优先取回未售出的代币
+        Ok(ProceedsWithdrawn {
+            sale: self.sale.key(),
+            owner: self.owner.key(),
+            token_mint: self.token_mint.key(),
+            buy_token_mint: self.buy_token_mint.key(),
+            unsold_amount: sale_balance,
+            proceeds_amount: if sale_balance > 0 { 0 } else { contract_balance },
+            timestamp: current_time,
+        })
     }
 }
\ No newline at end of file
@@ -1,12 +1,21 @@
 use super::SaleAccount;
+use super::SaleStatus;
 use super::UserPurchase;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount, Mint};
-use anchor_lang::solana_program::program::invoke_signed;
+use anchor_lang::system_program;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface, TransferChecked, transfer_checked};
 use anchor_spl::associated_token::AssociatedToken;
 
 use super::error::ErrorCode;
+use crate::events::{Refunded, TokensClaimed};
 
+/// 买家结算产生的事件，退款和领取二选一
+pub enum WithdrawTokensEvent {
+    Claimed(TokensClaimed),
+    Refunded(Refunded),
+}
+
+#[event_cpi]
 #[derive(Accounts)]
 pub struct WithdrawTokens<'info> {
     #[account(
@@ -17,23 +26,33 @@ pub struct WithdrawTokens<'info> {
 /// CHECK:` doc comment explaining why no checks through types are necessary.
     #[account(
         mut,
-        seeds = [crate::TOKEN_SEED], 
+        seeds = [crate::VAULT_AUTHORITY, sale.key().as_ref()], 
+        bump,
+    )]
+    pub pda_account: AccountInfo<'info>, //销售金库pda账户，每个销售独立
+
+    /// 原生 SOL 支付时存放买家 lamports 的金库
+    #[account(
+        mut,
+        seeds = [crate::SOL_VAULT, sale.key().as_ref()],
         bump,
     )]
-    pub pda_account: AccountInfo<'info>, //合约pda账户
+    pub sol_vault: SystemAccount<'info>,
     
 
     #[account(
         mut,
         constraint = token_mint.key() == sale.token_mint,
+        mint::token_program = token_program,
     )]
-    pub token_mint: Account<'info, Mint>,
+    pub token_mint: InterfaceAccount<'info, Mint>,
     
     #[account(
         mut,
         constraint = buy_token_mint.key() == sale.buy_token_mint,
+        mint::token_program = buy_token_program,
     )]
-    pub buy_token_mint: Account<'info, Mint>,
+    pub buy_token_mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
     pub owner: Signer<'info>,
@@ -41,30 +60,36 @@ pub struct WithdrawTokens<'info> {
     #[account(
         mut,
         constraint = owner_token_account.owner == owner.key(),
-        constraint = owner_token_account.mint == token_mint.key()
+        constraint = owner_token_account.mint == token_mint.key(),
+        token::token_program = token_program,
     )]
-    pub owner_token_account: Account<'info, TokenAccount>,
+    pub owner_token_account: InterfaceAccount<'info, TokenAccount>,
     
     #[account(
         mut,
         constraint = sale_token_account.owner == pda_account.key(),
-        constraint = sale_token_account.mint == token_mint.key()
+        constraint = sale_token_account.mint == token_mint.key(),
+        token::token_program = token_program,
     )]
-    pub sale_token_account: Account<'info, TokenAccount>,
+    pub sale_token_account: InterfaceAccount<'info, TokenAccount>,
 
+    /// 原生 SOL 支付时不需要，退款直接以 lamports 支付
     #[account(
         mut,
         constraint = refund_token_account.owner == owner.key(),
-        constraint = refund_token_account.mint == buy_token_mint.key()
+        constraint = refund_token_account.mint == buy_token_mint.key(),
+        token::token_program = buy_token_program,
     )]
-    pub refund_token_account: Account<'info, TokenAccount>,
+    pub refund_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
 
+    /// 原生 SOL 支付时不需要
     #[account(
         mut,
         constraint = contract_token_account.owner == pda_account.key(),
-        constraint = contract_token_account.mint == buy_token_mint.key()
+        constraint = contract_token_account.mint == buy_token_mint.key(),
+        token::token_program = buy_token_program,
     )]
-    pub contract_token_account: Account<'info, TokenAccount>,
+    pub contract_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
     
     #[account(
         mut,
@@ -76,16 +101,23 @@ pub struct WithdrawTokens<'info> {
     pub user_purchase: Account<'info, UserPurchase>,
     
     pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
+    /// 销售代币所属的 token program
+    pub token_program: Interface<'info, TokenInterface>,
+    /// 支付代币所属的 token program
+    pub buy_token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
 }
 
 impl<'info> WithdrawTokens<'info> {
-    pub fn process(&mut self,bump_seed:u8) -> Result<()> {
+    pub fn process(&mut self,bump_seed:u8, sol_vault_bump: u8) -> Result<WithdrawTokensEvent> {
         let current_time = Clock::get()?.unix_timestamp;
         
-        // 检查销售是否已结束
-        if current_time < self.sale.end_time {
+        // 销售结束或取消后判定结果，结果只判定一次，之后所有买家按同一结果结算
+        let status = self.sale.refresh_status(current_time)?;
+        if !matches!(
+            status,
+            SaleStatus::Failed | SaleStatus::Cancelled | SaleStatus::SoldOut | SaleStatus::Succeeded | SaleStatus::LiquiditySeeded
+        ) {
             msg!("Sale has not ended yet.");
             return Err(ErrorCode::SaleNotEnded.into());
         }
@@ -96,93 +128,130 @@ impl<'info> WithdrawTokens<'info> {
             return Err(ErrorCode::UserNotPurchased.into());
         }   
 
+        // 每个购买记录独立结算，已退款或已全部领取的不能重复结算
+        if self.user_purchase.is_claim {
+            msg!("Purchase already settled.");
+            return Err(ErrorCode::AlreadyClaimed.into());
+        }
     
-        // 如果还有剩余代币（未卖完）
-        if self.sale.remaining_amount > 0 {
-            // 验证剩余代币数量是否与销售记录一致
-            // if sale_balance != self.sale.remaining_amount {
-            //     msg!("Token balance mismatch.");
-            //     return Err(ErrorCode::BalanceMismatch.into());
-            // }
-
+        // 未达到软顶或已取消，销售失败，退款
+        let event = if !self.sale.is_successful() {
             // 计算用户应得的退款金额
             let refund_amount = self.user_purchase.purchase_amount;
 
-            let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED,  &[bump_seed]]];
-
-            // 生成从 GDTC 托管账户到用户 LP Token 账户的转账指令
-            let transfer_instruction = spl_token::instruction::transfer(
-                &self.token_program.key(),
-                &self.contract_token_account.key(),
-                &self.refund_token_account.key(),
-                &self.sale.key(),
-                &[],
-                refund_amount as u64, 
-            )?;
-       
-            // 执行带签名的 CPI 调用
-            invoke_signed(
-                &transfer_instruction,
-                &[
-                   self.token_program.to_account_info(),
-                   self.contract_token_account.to_account_info(),
-                   self.refund_token_account.to_account_info(),
-                   self.sale.to_account_info(),
-                ],
-                signer_seeds,
-            )?;
+            if self.sale.native_payment {
+                // 原生 SOL 支付，直接从 SOL 金库退还 lamports
+                let sale_key = self.sale.key();
+                let signer_seeds: &[&[&[u8]]] = &[&[crate::SOL_VAULT, sale_key.as_ref(), &[sol_vault_bump]]];
 
-            // 更新用户购买记录
-            self.user_purchase.purchase_amount = 0;
-            self.user_purchase.token_amount = 0;
+                system_program::transfer(
+                    CpiContext::new_with_signer(
+                        self.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: self.sol_vault.to_account_info(),
+                            to: self.owner.to_account_info(),
+                        },
+                        signer_seeds,
+                    ),
+                    refund_amount,
+                )?;
+            } else {
+                let sale_key = self.sale.key();
+                let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
+
+                let contract_token_account = self.contract_token_account
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingTokenAccount)?;
+                let refund_token_account = self.refund_token_account
+                    .as_ref()
+                    .ok_or(ErrorCode::MissingTokenAccount)?;
+
+                // 从合约支付代币账户退款到用户账户
+                let transfer_ctx = CpiContext::new_with_signer(
+                    self.buy_token_program.to_account_info(),
+                    TransferChecked {
+                        from: contract_token_account.to_account_info(),
+                        mint: self.buy_token_mint.to_account_info(),
+                        to: refund_token_account.to_account_info(),
+                        authority: self.pda_account.to_account_info(),
+                    },
+                    signer_seeds,
+                );
+                transfer_checked(transfer_ctx, refund_amount, self.buy_token_mint.decimals)?;
+            }
+
+            // 标记已退款，保留购买记录
+            self.user_purchase.is_claim = true;
+            self.sale.unsettled_purchases = self.sale.unsettled_purchases
+                .checked_sub(1)
+                .ok_or(ErrorCode::Overflow)?;
 
             msg!("Refunded {} buy tokens to user", refund_amount);
+
+            WithdrawTokensEvent::Refunded(Refunded {
+                sale: self.sale.key(),
+                buyer: self.owner.key(),
+                buy_token_mint: self.buy_token_mint.key(),
+                amount: refund_amount,
+                timestamp: current_time,
+            })
         } else {
-            // 如果代币已全部售出，发放用户购买的代币
-            let token_amount = self.user_purchase.token_amount;
+            // 达到软顶，销售成功，流动性注入后发放用户购买的代币
+            if status != SaleStatus::LiquiditySeeded {
+                msg!("Pool not seeded.");
+                return Err(ErrorCode::PoolNotSeeded.into());
+            }
+
+            // 只发放新解锁的部分，可多次领取
+            let vested_amount = self.sale.vested_amount(self.user_purchase.token_amount, current_time)?;
+            let token_amount = vested_amount
+                .checked_sub(self.user_purchase.claimed_amount)
+                .ok_or(ErrorCode::Overflow)?;
+
+            if token_amount == 0 {
+                msg!("No vested tokens to claim.");
+                return Err(ErrorCode::NoTokensToWithdraw.into());
+            }
             
+            let sale_key = self.sale.key();
+            let signer_seeds: &[&[&[u8]]] = &[&[crate::VAULT_AUTHORITY, sale_key.as_ref(), &[bump_seed]]];
+
             // 转移代币到用户账户
-            // transfer(
-            //     self.into_transfer_tokens_context(),
-            //     token_amount
-            // )?;
-
-            let signer_seeds: &[&[&[u8]]] = &[&[crate::TOKEN_SEED,   &[bump_seed]]];
-
-            // 生成从 GDTC 托管账户到用户 LP Token 账户的转账指令
-            let transfer_instruction = spl_token::instruction::transfer(
-                &self.token_program.key(),
-                &self.sale_token_account.key(),
-                &self.owner_token_account.key(),
-                &self.sale.key(),
-                &[],
-                token_amount as u64, 
-            )?;
-       
-            // 执行带签名的 CPI 调用
-            invoke_signed(
-                &transfer_instruction,
-                &[
-                   self.token_program.to_account_info(),
-                   self.sale_token_account.to_account_info(),
-                   self.owner_token_account.to_account_info(),
-                   self.sale.to_account_info(),
-                ],
+            let transfer_ctx = CpiContext::new_with_signer(
+                self.token_program.to_account_info(),
+                TransferChecked {
+                    from: self.sale_token_account.to_account_info(),
+                    mint: self.token_mint.to_account_info(),
+                    to: self.owner_token_account.to_account_info(),
+                    authority: self.pda_account.to_account_info(),
+                },
                 signer_seeds,
-            )?;
+            );
+            transfer_checked(transfer_ctx, token_amount, self.token_mint.decimals)?;
 
             // 更新用户购买记录
-            self.user_purchase.purchase_amount = 0;
-            self.user_purchase.token_amount = 0;
+            self.user_purchase.claimed_amount = vested_amount;
+            self.user_purchase.is_claim = vested_amount == self.user_purchase.token_amount;
+            if self.user_purchase.is_claim {
+                self.sale.unsettled_purchases = self.sale.unsettled_purchases
+                    .checked_sub(1)
+                    .ok_or(ErrorCode::Overflow)?;
+            }
 
             msg!("Distributed {} tokens to user", token_amount);
-        }
 
-        // 更新销售账户状态
-        self.sale.remaining_amount = 0;
-        self.sale.is_active = false;
+            WithdrawTokensEvent::Claimed(TokensClaimed {
+                sale: self.sale.key(),
+                buyer: self.owner.key(),
+                token_mint: self.token_mint.key(),
+                amount: token_amount,
+                claimed_amount: vested_amount,
+                total_amount: self.user_purchase.token_amount,
+                timestamp: current_time,
+            })
+        };
 
-        Ok(())
+        Ok(event)
     }
 
  